@@ -0,0 +1,232 @@
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+use image::codecs::hdr::HdrEncoder;
+use image::{ImageResult, Rgb, Rgba32FImage, RgbaImage};
+
+use rust_tracer::color::ToneMapping;
+use rust_tracer::frame_buffer::create_frame_buffer;
+use rust_tracer::scene::camera::Projection;
+use rust_tracer::scene::filter::Filter;
+use rust_tracer::scene::scene::{create_test_scene, Integrator, Scene};
+
+const DEFAULT_WIDTH: u32 = 800;
+const DEFAULT_HEIGHT: u32 = 600;
+
+const USAGE: &str = "Render the scene without a window and write it to an image file.
+
+USAGE:
+    render [OPTIONS] [SCENE]
+
+ARGS:
+    <SCENE>                  scene file to render, renders the built-in test scene if omitted
+
+OPTIONS:
+    -w, --width <PIXELS>     width of the image (default 800)
+    -h, --height <PIXELS>    height of the image (default 600)
+    -d, --depth <BOUNCES>    maximum ray depth (default 4, or the scene file's max_depth)
+    -i, --integrator <NAME>  whitted or path (default whitted, or the scene file's integrator)
+        --projection <NAME>  perspective, orthographic, fisheye or equirectangular
+                             (default perspective, or the scene file's camera projection)
+    -s, --spp <COUNT>        jittered samples per pixel and pass (default 1, or the scene file's
+                             samples_per_pixel)
+    -f, --filter <NAME>      box, tent, gaussian or mitchell (default box, or the scene file's filter)
+    -g, --glossy-samples <COUNT>
+                             reflection rays of the Whitted shader on rough surfaces (default 8, or
+                             the scene file's glossy_samples)
+    -a, --anisotropy <RATIO> longest texture footprint, relative to its width, that is filtered
+                             without blurring, 1 for trilinear filtering (default 1, or the scene
+                             file's max_anisotropy)
+    -p, --passes <COUNT>     number of passes averaged into the image, the path tracer
+                             needs many to converge (default 1)
+    -t, --tone-map <NAME>    clamp, reinhard or aces (default clamp, or the scene file's tone_mapping)
+    -e, --exposure <STOPS>   brightness change in stops (default 0, or the scene file's exposure)
+    -o, --output <PATH>      output file, the format is taken from the extension
+                             (png, jpg, bmp, tga, exr, hdr, ...) (default render.png),
+                             exr and hdr files get the linear colors without tone mapping
+        --help               print this message";
+
+struct Options {
+    width: u32,
+    height: u32,
+    depth: Option<i32>,
+    integrator: Option<Integrator>,
+    passes: u32,
+    projection: Option<Projection>,
+    samples_per_pixel: Option<u32>,
+    filter: Option<Filter>,
+    glossy_samples: Option<u32>,
+    max_anisotropy: Option<u32>,
+    tone_mapping: Option<ToneMapping>,
+    exposure: Option<f32>,
+    output: PathBuf,
+    scene: Option<PathBuf>,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        width: DEFAULT_WIDTH,
+        height: DEFAULT_HEIGHT,
+        depth: None,
+        integrator: None,
+        passes: 1,
+        projection: None,
+        samples_per_pixel: None,
+        filter: None,
+        glossy_samples: None,
+        max_anisotropy: None,
+        tone_mapping: None,
+        exposure: None,
+        output: PathBuf::from("render.png"),
+        scene: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+        match arg.as_str() {
+            "-w" | "--width" => options.width = parse_number(&arg, &value(&arg)?)?,
+            "-h" | "--height" => options.height = parse_number(&arg, &value(&arg)?)?,
+            "-d" | "--depth" => options.depth = Some(parse_number(&arg, &value(&arg)?)?),
+            "-i" | "--integrator" => options.integrator = Some(value(&arg)?.parse()?),
+            "--projection" => options.projection = Some(value(&arg)?.parse()?),
+            "-s" | "--spp" => options.samples_per_pixel = Some(parse_number(&arg, &value(&arg)?)?),
+            "-f" | "--filter" => options.filter = Some(value(&arg)?.parse()?),
+            "-g" | "--glossy-samples" => options.glossy_samples = Some(parse_number(&arg, &value(&arg)?)?),
+            "-a" | "--anisotropy" => options.max_anisotropy = Some(parse_number(&arg, &value(&arg)?)?),
+            "-p" | "--passes" => options.passes = parse_number(&arg, &value(&arg)?)?,
+            "-t" | "--tone-map" => options.tone_mapping = Some(value(&arg)?.parse()?),
+            "-e" | "--exposure" => options.exposure = Some(parse_number(&arg, &value(&arg)?)?),
+            "-o" | "--output" => options.output = PathBuf::from(value(&arg)?),
+            "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown argument '{}'", arg)),
+            _ if options.scene.is_none() => options.scene = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    if options.width == 0 || options.height == 0 {
+        return Err("width and height must be greater than 0".to_string());
+    }
+    if options.passes == 0 || options.samples_per_pixel == Some(0) {
+        return Err("passes and samples per pixel must be greater than 0".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, name))
+}
+
+fn main() {
+    env_logger::init();
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("error: {}\n\n{}", err, USAGE);
+        process::exit(2);
+    });
+
+    let mut scene = match &options.scene {
+        Some(path) => Scene::load(path).unwrap_or_else(|err| {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }),
+        None => Scene::create(0, 0)
+            .and_then(|mut scene| create_test_scene(&mut scene).map(|_| scene))
+            .unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }),
+    };
+    scene.width = options.width as i32;
+    scene.height = options.height as i32;
+    if let Some(depth) = options.depth {
+        scene.max_depth = depth;
+    }
+    if let Some(integrator) = options.integrator {
+        scene.integrator = integrator;
+    }
+    if let Some(projection) = options.projection {
+        scene.camera.projection = projection;
+    }
+    if let Some(samples_per_pixel) = options.samples_per_pixel {
+        scene.samples_per_pixel = samples_per_pixel;
+    }
+    if let Some(glossy_samples) = options.glossy_samples {
+        scene.glossy_samples = glossy_samples;
+    }
+    if let Some(max_anisotropy) = options.max_anisotropy {
+        scene.max_anisotropy = max_anisotropy;
+    }
+    if let Some(filter) = options.filter {
+        scene.filter = filter;
+    }
+    if let Some(tone_mapping) = options.tone_mapping {
+        scene.tone_mapper.tone_mapping = tone_mapping;
+    }
+    if let Some(exposure) = options.exposure {
+        scene.tone_mapper.exposure = exposure;
+    }
+
+    if scene.texture_count() > 0 {
+        println!(
+            "loaded {} textures ({:.1} MiB with mip maps and the sky)",
+            scene.texture_count(),
+            scene.texture_memory() as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    let start = Instant::now();
+    let mut passes = create_frame_buffer(scene.width, scene.height);
+    for _ in 0..options.passes {
+        scene.render_pass(&mut passes);
+    }
+    println!(
+        "rendered {}x{} ({} passes, {} samples per pixel) in {:.2?}",
+        options.width,
+        options.height,
+        options.passes,
+        scene.samples_per_pixel,
+        start.elapsed()
+    );
+
+    let extension = options
+        .output
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let result = match extension.as_str() {
+        // floating point formats keep the full range, tone mapping is left to the viewer
+        "exr" => Rgba32FImage::from_raw(options.width, options.height, passes.to_linear_rgba(&scene.tone_mapper))
+            .expect("frame buffer matches the image dimensions")
+            .save(&options.output),
+        "hdr" => write_hdr(&options.output, options.width, options.height, &passes.to_linear_rgba(&scene.tone_mapper)),
+        _ => {
+            let mut frame = vec![0u8; options.width as usize * options.height as usize * 4];
+            passes.write_rgba(&mut frame, &scene.tone_mapper);
+            RgbaImage::from_raw(options.width, options.height, frame)
+                .expect("frame buffer matches the image dimensions")
+                .save(&options.output)
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("error: could not write {}: {}", options.output.display(), err);
+        process::exit(1);
+    }
+    println!("written to {}", options.output.display());
+}
+
+/// `image::save` can't write radiance files, so this goes through the encoder directly.
+fn write_hdr(path: &Path, width: u32, height: u32, rgba: &[f32]) -> ImageResult<()> {
+    let pixels: Vec<Rgb<f32>> = rgba.chunks(4).map(|c| Rgb([c[0], c[1], c[2]])).collect();
+    let file = BufWriter::new(File::create(path)?);
+    HdrEncoder::new(file).encode(&pixels, width as usize, height as usize)
+}
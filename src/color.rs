@@ -0,0 +1,107 @@
+use std::str::FromStr;
+use glam::Vec3A;
+use serde::Deserialize;
+
+/// Converts an sRGB encoded value in [0, 1] to linear light.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts linear light in [0, 1] to the sRGB encoding used by displays and 8 bit image files.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Operator that compresses linear HDR colors into the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMapping {
+    /// everything above 1 saturates
+    Clamp,
+    /// `c / (1 + c)` per channel, never saturates
+    Reinhard,
+    /// Narkowicz' fit of the ACES filmic curve, more contrast than Reinhard
+    #[serde(rename = "aces")]
+    AcesFilmic,
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: Vec3A) -> Vec3A {
+        let color = color.max(Vec3A::ZERO);
+        let mapped = match self {
+            ToneMapping::Clamp => color,
+            ToneMapping::Reinhard => color / (Vec3A::ONE + color),
+            ToneMapping::AcesFilmic => {
+                let a = color * (2.51 * color + Vec3A::splat(0.03));
+                let b = color * (2.43 * color + Vec3A::splat(0.59)) + Vec3A::splat(0.14);
+                a / b
+            }
+        };
+        mapped.min(Vec3A::ONE)
+    }
+
+    /// the next operator, for cycling through them in the window
+    pub fn next(&self) -> ToneMapping {
+        match self {
+            ToneMapping::Clamp => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::AcesFilmic,
+            ToneMapping::AcesFilmic => ToneMapping::Clamp,
+        }
+    }
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(ToneMapping::Clamp),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::AcesFilmic),
+            _ => Err(format!("unknown tone mapping '{}', expected 'clamp', 'reinhard' or 'aces'", s)),
+        }
+    }
+}
+
+/// Turns linear scene colors into display colors: exposure, tone mapping and sRGB encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapper {
+    pub tone_mapping: ToneMapping,
+    /// in stops, every step of 1 doubles the brightness
+    pub exposure: f32,
+}
+
+impl ToneMapper {
+    /// linear color scaled by the exposure, before tone mapping
+    pub fn expose(&self, color: Vec3A) -> Vec3A {
+        color * self.exposure.exp2()
+    }
+
+    /// sRGB encoded display color in [0, 1]
+    pub fn map(&self, color: Vec3A) -> Vec3A {
+        let c = self.tone_mapping.apply(self.expose(color));
+        Vec3A::new(linear_to_srgb(c.x), linear_to_srgb(c.y), linear_to_srgb(c.z))
+    }
+
+    pub fn map_to_rgba(&self, color: Vec3A) -> [u8; 4] {
+        let c = self.map(color) * 255.0 + Vec3A::splat(0.5);
+        [c.x as u8, c.y as u8, c.z as u8, 0xff]
+    }
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        ToneMapper {
+            tone_mapping: ToneMapping::Clamp,
+            exposure: 0.0,
+        }
+    }
+}
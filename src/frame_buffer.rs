@@ -1,24 +1,70 @@
+use glam::Vec3A;
+use rayon::prelude::*;
+use crate::color::ToneMapper;
+
+/// Linear HDR RGB buffer that accumulates samples over several render passes.
 pub struct FrameBuffer {
     pub width: i32,
     pub height: i32,
-    pixels: Vec<i64>
+    /// number of passes added to every pixel
+    pub samples: u32,
+    /// sum of all samples
+    pixels: Vec<Vec3A>
 }
 
 impl FrameBuffer {
-    pub fn set_pixel(&mut self, x: i32, y: i32, color: i64) {
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Vec3A) {
         self.pixels[(x + y * self.width) as usize] = color;
     }
 
-    pub fn get_pixel(&self, x: i32, y: i32) -> i64 {
-        return self.pixels[(x + y * self.width) as usize];
+    /// average of the samples of a pixel
+    pub fn get_pixel(&self, x: i32, y: i32) -> Vec3A {
+        self.pixels[(x + y * self.width) as usize] / self.samples.max(1) as f32
+    }
+
+    /// Drops all samples, e.g. because the camera moved.
+    pub fn clear(&mut self) {
+        self.samples = 0;
+        self.pixels.iter_mut().for_each(|p| *p = Vec3A::ZERO);
+    }
+
+    /// Changes the size, this drops all samples.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        *self = create_frame_buffer(width, height);
+    }
+
+    /// Mutable rows of the sums for the render threads, used by the renderer to add a pass.
+    pub fn par_rows_mut(&mut self) -> rayon::slice::ChunksMut<'_, Vec3A> {
+        self.pixels.par_chunks_mut(self.width.max(1) as usize)
+    }
+
+    /// Writes the tone mapped, sRGB encoded average of the samples as RGBA bytes into `screen`.
+    pub fn write_rgba(&self, screen: &mut [u8], tone_mapper: &ToneMapper) {
+        let scale = 1.0 / self.samples.max(1) as f32;
+        screen.par_chunks_mut(4).zip(self.pixels.par_iter()).for_each(|(pixel, color_vec)| {
+            pixel.copy_from_slice(&tone_mapper.map_to_rgba(*color_vec * scale))
+        });
+    }
+
+    /// Linear RGBA floats of the exposed average without tone mapping, for HDR image files.
+    pub fn to_linear_rgba(&self, tone_mapper: &ToneMapper) -> Vec<f32> {
+        let scale = 1.0 / self.samples.max(1) as f32;
+        self.pixels
+            .iter()
+            .flat_map(|color_vec| {
+                let c = tone_mapper.expose(*color_vec * scale);
+                [c.x, c.y, c.z, 1.0]
+            })
+            .collect()
     }
 }
 
 pub fn create_frame_buffer(width: i32, height: i32) -> FrameBuffer {
-    let pixels: Vec<i64> = Vec::with_capacity((width * height) as usize);
-    return FrameBuffer {
+    let pixels: Vec<Vec3A> = vec![Vec3A::ZERO; (width * height).max(0) as usize];
+    FrameBuffer {
         width,
         height,
+        samples: 0,
         pixels
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,64 @@
+use glam::Vec3A;
+
+/// Axis aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3A,
+    pub max: Vec3A,
+}
+
+impl Aabb {
+    pub fn create(min: Vec3A, max: Vec3A) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// box that contains nothing, the neutral element of `union`
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vec3A::splat(f32::INFINITY),
+            max: Vec3A::splat(f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn grow(&self, point: Vec3A) -> Aabb {
+        Aabb {
+            min: self.min.min(point),
+            max: self.max.max(point),
+        }
+    }
+
+    /// false for unbounded objects like planes
+    pub fn is_finite(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
+    }
+
+    pub fn centroid(&self) -> Vec3A {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = (self.max - self.min).max(Vec3A::ZERO);
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Slab test. `inv_dir` is `1.0 / ray.dir`, returns the distance at which the ray enters the box
+    /// if that happens before `t_max`.
+    pub fn intersect(&self, org: Vec3A, inv_dir: Vec3A, t_max: f32) -> Option<f32> {
+        let t0 = (self.min - org) * inv_dir;
+        let t1 = (self.max - org) * inv_dir;
+        let t_near = t0.min(t1).max_element().max(0.0);
+        let t_far = t0.max(t1).min_element().min(t_max);
+        if t_near <= t_far {
+            Some(t_near)
+        } else {
+            None
+        }
+    }
+}
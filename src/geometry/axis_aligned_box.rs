@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3A};
+
+use crate::geometry::aabb::Aabb;
+use crate::geometry::ray::Ray;
+use crate::geometry::traceable::{tangent_frame, Traceable};
+use crate::scene::material::Material;
+
+/// Solid box with faces parallel to the axes, the texture repeats every `texture_scale` units on every face.
+pub struct AxisAlignedBox {
+    pub min: Vec3A,
+    pub max: Vec3A,
+    pub texture_scale: f32,
+    pub mat: Arc<Material>,
+}
+
+impl AxisAlignedBox {
+    pub fn create(min: Vec3A, max: Vec3A, texture_scale: f32, mat: Arc<Material>) -> AxisAlignedBox {
+        AxisAlignedBox {
+            min: min.min(max),
+            max: min.max(max),
+            texture_scale,
+            mat,
+        }
+    }
+
+    /// index of the axis of the face the point lies on, the one whose plane is closest, which also works for flat
+    /// boxes
+    fn face_axis(&self, position: &Vec3A) -> usize {
+        let d = (*position - self.min).abs().min((*position - self.max).abs());
+        if d.x <= d.y && d.x <= d.z {
+            0
+        } else if d.y <= d.z {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+impl Traceable for AxisAlignedBox {
+    fn intersect(&self, ray: &Ray, t: &mut f32) -> bool {
+        let inv_dir = ray.dir.recip();
+        let t0 = (self.min - ray.org) * inv_dir;
+        let t1 = (self.max - ray.org) * inv_dir;
+        let t_near = t0.min(t1).max_element();
+        let t_far = t0.max(t1).min_element();
+        if t_near > t_far {
+            return false;
+        }
+        // rays starting inside the box hit its far side
+        *t = if t_near > 0.00001 { t_near } else { t_far };
+        *t > 0.00001
+    }
+
+    fn intersection_normal(&self, ray: &Ray, intersection_point: Vec3A) -> (Vec3A, Vec3A) {
+        let axis = self.face_axis(&intersection_point);
+        let center = (self.min + self.max) * 0.5;
+        let mut normal = Vec3A::ZERO;
+        normal[axis] = if self.max[axis] > self.min[axis] {
+            (intersection_point[axis] - center[axis]).signum()
+        } else {
+            // both faces of a flat box lie in one plane, take the one the ray sees
+            -ray.dir[axis].signum()
+        };
+
+        let dot = ray.dir.dot(normal);
+        let reflection = ray.dir - 2.0 * dot * normal;
+        (normal, reflection)
+    }
+
+    fn update(&mut self) {}
+
+    fn get_mat(&self) -> Arc<Material> {
+        self.mat.clone()
+    }
+
+    fn get_texture_coord(&self, position: &Vec3A) -> Vec2 {
+        let p = (*position - self.min) / self.texture_scale;
+        // v points down on the side faces so textures are upright
+        let (u, v) = match self.face_axis(position) {
+            0 => (p.z, -p.y),
+            1 => (p.x, p.z),
+            _ => (p.x, -p.y),
+        };
+        Vec2::new(u, v)
+    }
+
+    fn get_object_coord(&self, position: &Vec3A) -> Vec3A {
+        *position - self.min
+    }
+
+    fn tangents(&self, position: &Vec3A, normal: Vec3A) -> (Vec3A, Vec3A) {
+        // the axes `get_texture_coord` takes u and v from
+        let (du, dv) = match self.face_axis(position) {
+            0 => (Vec3A::Z, -Vec3A::Y),
+            1 => (Vec3A::X, Vec3A::Z),
+            _ => (Vec3A::X, -Vec3A::Y),
+        };
+        tangent_frame(normal, du, dv)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::create(self.min, self.max)
+    }
+}
@@ -0,0 +1,232 @@
+use glam::Vec3A;
+
+use crate::geometry::aabb::Aabb;
+use crate::geometry::ray::Ray;
+use crate::geometry::traceable::Traceable;
+
+/// number of buckets the centroids are sorted into when searching for a split
+const BIN_COUNT: usize = 12;
+/// leaves with more objects are always split
+const MAX_LEAF_SIZE: usize = 4;
+/// cost of visiting a node relative to intersecting an object
+const TRAVERSAL_COST: f32 = 0.5;
+
+struct Node {
+    bounds: Aabb,
+    /// leaf: first entry in `Bvh::indices`, inner node: index of the second child. The first child directly follows its parent.
+    offset: usize,
+    /// number of objects in a leaf, 0 for inner nodes
+    count: usize,
+}
+
+/**
+Bounding volume hierarchy over the objects of a scene, built with the surface area heuristic (SAH).
+It only stores indices into the object list it was built from, so it has to be rebuilt when objects are added
+and refitted when they move. Objects without finite bounds (planes) are kept out of the tree and tested for every ray.
+ */
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn build(objects: &[Box<dyn Traceable>]) -> Bvh {
+        let bounds: Vec<Aabb> = objects.iter().map(|obj| obj.bounding_box()).collect();
+        let centroids: Vec<Vec3A> = bounds.iter().map(|b| b.centroid()).collect();
+        let (indices, unbounded) = (0..objects.len()).partition::<Vec<usize>, _>(|&i| bounds[i].is_finite());
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(indices.len() * 2),
+            indices,
+            unbounded,
+        };
+        if !bvh.indices.is_empty() {
+            bvh.build_node(&bounds, &centroids, 0, bvh.indices.len());
+        }
+        bvh
+    }
+
+    fn build_node(&mut self, bounds: &[Aabb], centroids: &[Vec3A], start: usize, end: usize) -> usize {
+        let node_index = self.nodes.len();
+        let node_bounds = self.indices[start..end].iter().fold(Aabb::empty(), |acc, &i| acc.union(&bounds[i]));
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            offset: start,
+            count: end - start,
+        });
+        if let Some(mid) = self.split(bounds, centroids, start, end, &node_bounds) {
+            self.build_node(bounds, centroids, start, mid);
+            let second = self.build_node(bounds, centroids, mid, end);
+            self.nodes[node_index].offset = second;
+            self.nodes[node_index].count = 0;
+        }
+        node_index
+    }
+
+    /// Partitions `indices[start..end]` at the cheapest binned SAH split and returns the index of the split,
+    /// or `None` if a leaf is cheaper.
+    fn split(&mut self, bounds: &[Aabb], centroids: &[Vec3A], start: usize, end: usize, node_bounds: &Aabb) -> Option<usize> {
+        let count = end - start;
+        if count <= 1 {
+            return None;
+        }
+        let centroid_bounds = self.indices[start..end].iter().fold(Aabb::empty(), |acc, &i| acc.grow(centroids[i]));
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let bin_of = |i: usize, axis: usize| -> usize {
+            let relative = (centroids[i][axis] - centroid_bounds.min[axis]) / extent[axis];
+            ((relative * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1)
+        };
+
+        // (cost, axis, last bin of the first child)
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let mut bin_bounds = [Aabb::empty(); BIN_COUNT];
+            let mut bin_counts = [0usize; BIN_COUNT];
+            for &i in self.indices[start..end].iter() {
+                let bin = bin_of(i, axis);
+                bin_bounds[bin] = bin_bounds[bin].union(&bounds[i]);
+                bin_counts[bin] += 1;
+            }
+
+            // sweep from the right so the cost of each split is known in the sweep from the left
+            let mut right_area = [0.0; BIN_COUNT];
+            let mut right_count = [0usize; BIN_COUNT];
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for bin in (1..BIN_COUNT).rev() {
+                acc = acc.union(&bin_bounds[bin]);
+                n += bin_counts[bin];
+                right_area[bin] = acc.surface_area();
+                right_count[bin] = n;
+            }
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for bin in 0..BIN_COUNT - 1 {
+                acc = acc.union(&bin_bounds[bin]);
+                n += bin_counts[bin];
+                if n == 0 || right_count[bin + 1] == 0 {
+                    continue;
+                }
+                let cost = acc.surface_area() * n as f32 + right_area[bin + 1] * right_count[bin + 1] as f32;
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, bin));
+                }
+            }
+        }
+
+        let area = node_bounds.surface_area();
+        let indices = &mut self.indices[start..end];
+        match best {
+            Some((cost, axis, split_bin)) => {
+                if count <= MAX_LEAF_SIZE && TRAVERSAL_COST * area + cost >= count as f32 * area {
+                    return None;
+                }
+                let mut mid = 0;
+                for k in 0..indices.len() {
+                    if bin_of(indices[k], axis) <= split_bin {
+                        indices.swap(mid, k);
+                        mid += 1;
+                    }
+                }
+                Some(start + mid)
+            }
+            // all centroids are at the same spot, only split if the leaf would get too big
+            None if count > MAX_LEAF_SIZE => Some(start + count / 2),
+            None => None,
+        }
+    }
+
+    /// Recomputes the bounds of all nodes after objects moved. The tree structure is kept, so this is much cheaper
+    /// than a rebuild but the tree gets worse if objects move far.
+    pub fn refit(&mut self, objects: &[Box<dyn Traceable>]) {
+        // children always have a higher index than their parent
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let bounds = if node.count > 0 {
+                self.indices[node.offset..node.offset + node.count]
+                    .iter()
+                    .fold(Aabb::empty(), |acc, &i| acc.union(&objects[i].bounding_box()))
+            } else {
+                self.nodes[index + 1].bounds.union(&self.nodes[node.offset].bounds)
+            };
+            self.nodes[index].bounds = bounds;
+        }
+    }
+
+    /// Finds the closest object hit by the ray with `t_min < t < t_max`. Returns `(t, index of the object)`.
+    pub fn intersect(&self, objects: &[Box<dyn Traceable>], ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, usize)> {
+        let mut closest = None;
+        let mut t_max = t_max;
+        self.traverse(ray, &mut t_max, |i, t_max| {
+            let mut t: f32 = 0.0;
+            if objects[i].intersect(ray, &mut t) && t > t_min && t < *t_max {
+                *t_max = t;
+                closest = Some((t, i));
+            }
+            false
+        });
+        closest
+    }
+
+    /// Checks if any object is hit by the ray with `t_min < t < t_max`, used for shadow rays.
+    pub fn occluded(&self, objects: &[Box<dyn Traceable>], ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let mut t_max = t_max;
+        let mut hit = false;
+        self.traverse(ray, &mut t_max, |i, t_max| {
+            let mut t: f32 = 0.0;
+            hit = objects[i].intersect(ray, &mut t) && t > t_min && t < *t_max;
+            hit
+        });
+        hit
+    }
+
+    /// Visits the objects in the leaves hit by the ray, nearer children first. `visit` may shrink `t_max`
+    /// and returns true to stop the traversal.
+    fn traverse<F: FnMut(usize, &mut f32) -> bool>(&self, ray: &Ray, t_max: &mut f32, mut visit: F) {
+        for &i in self.unbounded.iter() {
+            if visit(i, t_max) {
+                return;
+            }
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inv_dir = ray.dir.recip();
+        let mut stack = Vec::with_capacity(32);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds.intersect(ray.org, inv_dir, *t_max).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                for &i in self.indices[node.offset..node.offset + node.count].iter() {
+                    if visit(i, t_max) {
+                        return;
+                    }
+                }
+                continue;
+            }
+            let first = index + 1;
+            let second = node.offset;
+            let t_first = self.nodes[first].bounds.intersect(ray.org, inv_dir, *t_max);
+            let t_second = self.nodes[second].bounds.intersect(ray.org, inv_dir, *t_max);
+            match (t_first, t_second) {
+                (Some(t0), Some(t1)) if t1 < t0 => {
+                    stack.push(first);
+                    stack.push(second);
+                }
+                (Some(_), Some(_)) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                (Some(_), None) => stack.push(first),
+                (None, Some(_)) => stack.push(second),
+                (None, None) => {}
+            }
+        }
+    }
+}
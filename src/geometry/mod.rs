@@ -1,3 +1,8 @@
+pub mod aabb;
+pub mod axis_aligned_box;
+pub mod bvh;
+pub mod plane;
 pub mod ray;
 pub mod sphere;
-pub mod traceable;
\ No newline at end of file
+pub mod traceable;
+pub mod triangle;
\ No newline at end of file
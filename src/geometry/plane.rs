@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3A};
+
+use crate::geometry::aabb::Aabb;
+use crate::geometry::ray::Ray;
+use crate::geometry::traceable::{tangent_frame, Traceable};
+use crate::scene::material::Material;
+
+/// Infinite plane, the texture repeats every `texture_scale` units.
+pub struct Plane {
+    pub point: Vec3A,
+    pub normal: Vec3A,
+    pub texture_scale: f32,
+    pub mat: Arc<Material>,
+    u_axis: Vec3A,
+    v_axis: Vec3A,
+}
+
+impl Plane {
+    pub fn create(point: Vec3A, normal: Vec3A, texture_scale: f32, mat: Arc<Material>) -> Plane {
+        let normal = normal.normalize();
+        // any axis that is not parallel to the normal, a ground plane gets u along x and v along -z
+        let reference = if normal.x.abs() < 0.9 { Vec3A::X } else { Vec3A::Z };
+        let v_axis = normal.cross(reference).normalize();
+        let u_axis = v_axis.cross(normal);
+        Plane {
+            point,
+            normal,
+            texture_scale,
+            mat,
+            u_axis,
+            v_axis,
+        }
+    }
+}
+
+impl Traceable for Plane {
+    fn intersect(&self, ray: &Ray, t: &mut f32) -> bool {
+        let denom = self.normal.dot(ray.dir);
+        if denom.abs() < 1e-9 {
+            return false;
+        }
+        *t = (self.point - ray.org).dot(self.normal) / denom;
+        *t > 0.00001
+    }
+
+    fn intersection_normal(&self, ray: &Ray, _intersection_point: Vec3A) -> (Vec3A, Vec3A) {
+        let dot = ray.dir.dot(self.normal);
+        let reflection = ray.dir - 2.0 * dot * self.normal;
+        (self.normal, reflection)
+    }
+
+    fn update(&mut self) {}
+
+    fn get_mat(&self) -> Arc<Material> {
+        self.mat.clone()
+    }
+
+    fn get_texture_coord(&self, position: &Vec3A) -> Vec2 {
+        let d = (*position - self.point) / self.texture_scale;
+        Vec2::new(d.dot(self.u_axis), d.dot(self.v_axis))
+    }
+
+    fn get_object_coord(&self, position: &Vec3A) -> Vec3A {
+        *position - self.point
+    }
+
+    fn tangents(&self, _position: &Vec3A, normal: Vec3A) -> (Vec3A, Vec3A) {
+        tangent_frame(normal, self.u_axis, self.v_axis)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::create(Vec3A::splat(f32::NEG_INFINITY), Vec3A::splat(f32::INFINITY))
+    }
+}
@@ -11,3 +11,27 @@ impl Ray {
         self.org + self.dir * t
     }
 }
+
+/// Cone around a ray that covers one pixel, textures are filtered over its cross section where it hits a surface.
+/// Reflections and refractions keep the spread of the camera ray, as if every surface was flat.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RayCone {
+    /// width at the origin of the ray
+    pub width: f32,
+    /// growth of the width per unit of distance, the angle a pixel covers
+    pub spread: f32,
+}
+
+impl RayCone {
+    pub fn width_at(&self, t: f32) -> f32 {
+        self.width + self.spread * t
+    }
+
+    /// the cone of a ray that continues from distance `t`
+    pub fn advance(&self, t: f32) -> RayCone {
+        RayCone {
+            width: self.width_at(t),
+            spread: self.spread,
+        }
+    }
+}
@@ -1,12 +1,13 @@
 use core::f32::consts::PI;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use glam::{Vec2, Vec3A};
 use rand::Rng;
 use rand::rngs::OsRng;
 
+use crate::geometry::aabb::Aabb;
 use crate::geometry::ray::Ray;
-use crate::geometry::traceable::Traceable;
+use crate::geometry::traceable::{tangent_frame, Traceable};
 use crate::scene::material::Material;
 
 pub fn uv_map(n: &Vec3A) -> (f32, f32) {
@@ -21,11 +22,11 @@ pub struct Sphere {
     pub r: f32,
     pub r2: f32,
     t: i32,
-    pub mat: Rc<Material>,
+    pub mat: Arc<Material>,
 }
 
 impl Sphere {
-    pub fn create(center: Vec3A, r: f32, mat: Rc<Material>) -> Sphere {
+    pub fn create(center: Vec3A, r: f32, mat: Arc<Material>) -> Sphere {
         Sphere {
             center,
             org_center: center,
@@ -42,7 +43,8 @@ impl Traceable for Sphere {
     fn intersect(&self, ray: &Ray, t: &mut f32) -> bool {
         let l = self.center - ray.org;
         let t_ca = l.dot(ray.dir);
-        if t_ca < 0.0 {
+        let inside = l.length_squared() < self.r2;
+        if t_ca < 0.0 && !inside {
             return false;
         }
         let d2 = l.length_squared() - t_ca * t_ca;
@@ -56,8 +58,9 @@ impl Traceable for Sphere {
             std::mem::swap(&mut t0, &mut t1);
         }
 
-        *t = t0;
-        t0 > 0.00001
+        // rays starting inside the sphere (refraction) hit its far side
+        *t = if t0 > 0.00001 { t0 } else { t1 };
+        *t > 0.00001
     }
 
     fn intersection_normal(&self, ray: &Ray, intersection_point: Vec3A) -> (Vec3A, Vec3A) {
@@ -76,7 +79,7 @@ impl Traceable for Sphere {
         self.center = self.org_center + elevation;
     }
 
-    fn get_mat(&self) -> Rc<Material> {
+    fn get_mat(&self) -> Arc<Material> {
         self.mat.clone()
     }
 
@@ -86,4 +89,19 @@ impl Traceable for Sphere {
 
         Vec2::new(u, v)
     }
+
+    fn get_object_coord(&self, position: &Vec3A) -> Vec3A {
+        *position - self.center
+    }
+
+    fn tangents(&self, position: &Vec3A, normal: Vec3A) -> (Vec3A, Vec3A) {
+        // u goes around the y axis, v down towards -y
+        let d = *position - self.center;
+        tangent_frame(normal, Vec3A::new(d.z, 0.0, -d.x), -Vec3A::Y)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3A::splat(self.r);
+        Aabb::create(self.center - r, self.center + r)
+    }
 }
@@ -1,13 +1,46 @@
-use std::rc::Rc;
+use std::sync::Arc;
 use glam::{Vec2, Vec3A};
+use crate::geometry::aabb::Aabb;
 use crate::geometry::ray::Ray;
 use crate::scene::material::Material;
+use crate::scene::sampling::orthonormal_basis;
 
-pub trait Traceable {
+/// Traceables are shared between the render threads, so they must be `Send + Sync`.
+pub trait Traceable: Send + Sync {
     fn intersect(&self, ray: &Ray, t: &mut f32) -> bool;
     /// returns (normal, reflection)
     fn intersection_normal(&self, ray: &Ray, intersection_point: Vec3A) -> (Vec3A, Vec3A);
     fn update(&mut self);
-    fn get_mat(&self) -> Rc<Material>;
+    fn get_mat(&self) -> Arc<Material>;
     fn get_texture_coord(&self, position: &Vec3A) -> Vec2;
+    /// Position relative to the object, where procedural textures in object space are evaluated. It follows the
+    /// object when it moves. Objects without an origin of their own, like the triangles of a mesh, keep `position`.
+    fn get_object_coord(&self, position: &Vec3A) -> Vec3A {
+        *position
+    }
+    /// Tangent and bitangent at `position`, unit vectors perpendicular to `normal` along which the texture coordinates
+    /// u and v grow, see `tangent_frame`. Normal maps are oriented by them.
+    fn tangents(&self, position: &Vec3A, normal: Vec3A) -> (Vec3A, Vec3A);
+    /// box enclosing the object at its current position
+    fn bounding_box(&self) -> Aabb;
+}
+
+/// Tangent frame for the shading `normal` from the directions `du` and `dv` in which the texture coordinates grow on
+/// the surface, like the MikkTSpace tangents of glTF: the tangent is `du` made perpendicular to the normal and the
+/// bitangent is perpendicular to both, on the side of `dv`. Meshes pass `du` and `dv` averaged over the faces around
+/// the vertices, so the frame doesn't jump at the edges. Where the mapping is degenerate, like at the poles of a
+/// sphere, any frame around the normal is returned.
+pub fn tangent_frame(normal: Vec3A, du: Vec3A, dv: Vec3A) -> (Vec3A, Vec3A) {
+    let tangent = du - normal * normal.dot(du);
+    if tangent.length_squared() < 1e-12 || !tangent.is_finite() {
+        let bitangent = dv - normal * normal.dot(dv);
+        if bitangent.length_squared() < 1e-12 || !bitangent.is_finite() {
+            return orthonormal_basis(normal);
+        }
+        let bitangent = bitangent.normalize();
+        return (bitangent.cross(normal), bitangent);
+    }
+    let tangent = tangent.normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent, if bitangent.dot(dv) < 0.0 { -bitangent } else { bitangent })
 }
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3A};
+
+use crate::geometry::aabb::Aabb;
+use crate::geometry::ray::Ray;
+use crate::geometry::traceable::{tangent_frame, Traceable};
+use crate::scene::material::Material;
+
+/// Triangle of a mesh with optional per-vertex normals and texture coordinates.
+pub struct Triangle {
+    pub vertices: [Vec3A; 3],
+    /// per-vertex normals, the face normal is used if they are missing
+    pub normals: Option<[Vec3A; 3]>,
+    pub uvs: Option<[Vec2; 3]>,
+    /// per-vertex directions in which u and v grow, summed over the faces around the vertex like the normals, the
+    /// ones of the face are used if they are missing
+    pub vertex_tangents: Option<[(Vec3A, Vec3A); 3]>,
+    pub mat: Arc<Material>,
+    edge1: Vec3A,
+    edge2: Vec3A,
+    face_normal: Vec3A,
+    /// change of the position with the texture coordinates, zero without them
+    dp_du: Vec3A,
+    dp_dv: Vec3A,
+}
+
+impl Triangle {
+    /// The vertices are expected in counter clockwise order when looking at the front face.
+    pub fn create(vertices: [Vec3A; 3], normals: Option<[Vec3A; 3]>, uvs: Option<[Vec2; 3]>, mat: Arc<Material>) -> Triangle {
+        let edge1 = vertices[1] - vertices[0];
+        let edge2 = vertices[2] - vertices[0];
+        let (mut dp_du, mut dp_dv) = (Vec3A::ZERO, Vec3A::ZERO);
+        if let Some(uvs) = uvs {
+            // solve edge = dp_du * du + dp_dv * dv for both edges
+            let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+            let det = duv1.x * duv2.y - duv2.x * duv1.y;
+            if det.abs() > 1e-12 {
+                dp_du = (edge1 * duv2.y - edge2 * duv1.y) / det;
+                dp_dv = (edge2 * duv1.x - edge1 * duv2.x) / det;
+            }
+        }
+        Triangle {
+            vertices,
+            normals,
+            uvs,
+            vertex_tangents: None,
+            mat,
+            edge1,
+            edge2,
+            face_normal: edge1.cross(edge2).normalize(),
+            dp_du,
+            dp_dv,
+        }
+    }
+
+    /// change of the position with the texture coordinates u and v across the face, zero without texture coordinates
+    pub fn uv_derivatives(&self) -> (Vec3A, Vec3A) {
+        (self.dp_du, self.dp_dv)
+    }
+
+    /// barycentric coordinates of a point in the plane of the triangle
+    pub fn barycentric(&self, position: &Vec3A) -> Vec3A {
+        let d = *position - self.vertices[0];
+        let d11 = self.edge1.dot(self.edge1);
+        let d12 = self.edge1.dot(self.edge2);
+        let d22 = self.edge2.dot(self.edge2);
+        let d1 = d.dot(self.edge1);
+        let d2 = d.dot(self.edge2);
+        let denom = d11 * d22 - d12 * d12;
+        let v = (d22 * d1 - d12 * d2) / denom;
+        let w = (d11 * d2 - d12 * d1) / denom;
+        Vec3A::new(1.0 - v - w, v, w)
+    }
+}
+
+impl Traceable for Triangle {
+    /// Möller–Trumbore intersection, hits both faces
+    fn intersect(&self, ray: &Ray, t: &mut f32) -> bool {
+        let p = ray.dir.cross(self.edge2);
+        let det = self.edge1.dot(p);
+        if det.abs() < 1e-9 {
+            // parallel to the triangle
+            return false;
+        }
+        let inv_det = 1.0 / det;
+        let s = ray.org - self.vertices[0];
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return false;
+        }
+        let q = s.cross(self.edge1);
+        let v = ray.dir.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return false;
+        }
+        *t = self.edge2.dot(q) * inv_det;
+        *t > 0.00001
+    }
+
+    fn intersection_normal(&self, ray: &Ray, intersection_point: Vec3A) -> (Vec3A, Vec3A) {
+        let normal = match &self.normals {
+            Some(normals) => {
+                let b = self.barycentric(&intersection_point);
+                (normals[0] * b.x + normals[1] * b.y + normals[2] * b.z).normalize()
+            }
+            None => self.face_normal,
+        };
+
+        let dot = ray.dir.dot(normal);
+        let reflection = ray.dir - 2.0 * dot * normal;
+        (normal, reflection)
+    }
+
+    fn update(&mut self) {}
+
+    fn get_mat(&self) -> Arc<Material> {
+        self.mat.clone()
+    }
+
+    fn get_texture_coord(&self, position: &Vec3A) -> Vec2 {
+        match &self.uvs {
+            Some(uvs) => {
+                let b = self.barycentric(position);
+                uvs[0] * b.x + uvs[1] * b.y + uvs[2] * b.z
+            }
+            None => Vec2::ZERO,
+        }
+    }
+
+    fn tangents(&self, position: &Vec3A, normal: Vec3A) -> (Vec3A, Vec3A) {
+        match &self.vertex_tangents {
+            Some(tangents) => {
+                let b = self.barycentric(position);
+                let du = tangents[0].0 * b.x + tangents[1].0 * b.y + tangents[2].0 * b.z;
+                let dv = tangents[0].1 * b.x + tangents[1].1 * b.y + tangents[2].1 * b.z;
+                tangent_frame(normal, du, dv)
+            }
+            None => tangent_frame(normal, self.dp_du, self.dp_dv),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::empty()
+            .grow(self.vertices[0])
+            .grow(self.vertices[1])
+            .grow(self.vertices[2])
+    }
+}
@@ -1,3 +1,4 @@
+pub mod color;
 pub mod frame_buffer;
 pub mod geometry;
 pub mod tests;
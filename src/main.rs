@@ -9,13 +9,38 @@ use winit::event_loop::{ControlFlow, EventLoop};
 use winit_input_helper::WinitInputHelper;
 
 
-use rust_tracer::scene::scene::{create_test_scene, Scene};
+use rust_tracer::frame_buffer::create_frame_buffer;
+use rust_tracer::scene::scene::{create_test_scene, Integrator, Scene};
 
 const SCREEN_WIDTH: u32 = 800;
 const SCREEN_HEIGHT: u32 = 600;
 
 fn main() -> Result<(), Error> {
     env_logger::init();
+    // an optional scene file can be passed as the first argument
+    let mut scene = match std::env::args().nth(1) {
+        Some(path) => match Scene::load(&path) {
+            Ok(scene) => scene,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            let scene = Scene::create(SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32)
+                .and_then(|mut scene| create_test_scene(&mut scene).map(|_| scene));
+            match scene {
+                Ok(scene) => scene,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+    scene.width = SCREEN_WIDTH as i32;
+    scene.height = SCREEN_HEIGHT as i32;
+
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
     let (window, p_width, p_height, mut _hidpi_factor) =
@@ -25,15 +50,15 @@ fn main() -> Result<(), Error> {
 
     let mut pixels = Pixels::new(SCREEN_WIDTH, SCREEN_HEIGHT, surface_texture)?;
     let mut paused = false;
-    let mut scene = Scene::create(SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
-
-    create_test_scene(&mut scene);
+    // passes are accumulated while neither the camera nor the scene changes
+    let mut frame = create_frame_buffer(scene.width, scene.height);
 
     event_loop.run(move |event, _, control_flow| {
         // The one and only event that winit_input_helper doesn't have for us...
         if let Event::RedrawRequested(_) = event {
             // life.draw(pixels.get_frame());
-            scene.render(pixels.get_frame());
+            scene.render_pass(&mut frame);
+            frame.write_rgba(pixels.get_frame(), &scene.tone_mapper);
             if pixels
                 .render()
                 .map_err(|_| error!("pixels.render() failed"))
@@ -52,6 +77,7 @@ fn main() -> Result<(), Error> {
                 *control_flow = ControlFlow::Exit;
                 return;
             }
+            let camera_before = scene.camera.clone();
             if input.key_pressed(VirtualKeyCode::P) {
                 paused = !paused;
             }
@@ -59,6 +85,38 @@ fn main() -> Result<(), Error> {
                 // Space is frame-step, so ensure we're paused
                 paused = true;
             }
+            if input.key_pressed(VirtualKeyCode::I) {
+                scene.integrator = match scene.integrator {
+                    Integrator::Whitted => Integrator::PathTracer,
+                    Integrator::PathTracer => Integrator::Whitted,
+                };
+                println!("integrator: {:?}", scene.integrator);
+                frame.clear();
+            }
+            if input.key_pressed(VirtualKeyCode::A) {
+                // 1, 2, 4, 8, 16 samples per pixel
+                scene.samples_per_pixel = if scene.samples_per_pixel >= 16 { 1 } else { scene.samples_per_pixel * 2 };
+                println!("samples per pixel: {}", scene.samples_per_pixel);
+                frame.clear();
+            }
+            if input.key_pressed(VirtualKeyCode::F) {
+                scene.filter = scene.filter.next();
+                println!("filter: {:?}", scene.filter);
+                frame.clear();
+            }
+            // tone mapping only changes the output, the accumulated passes stay valid
+            if input.key_pressed(VirtualKeyCode::T) {
+                scene.tone_mapper.tone_mapping = scene.tone_mapper.tone_mapping.next();
+                println!("tone mapping: {:?}", scene.tone_mapper.tone_mapping);
+            }
+            if input.key_pressed(VirtualKeyCode::Equals) {
+                scene.tone_mapper.exposure += 0.5;
+                println!("exposure: {:+} stops", scene.tone_mapper.exposure);
+            }
+            if input.key_pressed(VirtualKeyCode::Minus) {
+                scene.tone_mapper.exposure -= 0.5;
+                println!("exposure: {:+} stops", scene.tone_mapper.exposure);
+            }
 
             if input.key_pressed(VirtualKeyCode::Left) {
                 scene.camera.go_left();
@@ -106,6 +164,31 @@ fn main() -> Result<(), Error> {
                 let dy = mouse_cell.1 - mouse_prev_cell.1;
                 scene.camera.turn(dx, dy);
             }
+            // right click focuses on the object under the cursor
+            if input.mouse_pressed(1) {
+                if let Some(point) = scene.pick(mouse_cell.0 as f32, mouse_cell.1 as f32) {
+                    scene.camera.focus_on(point);
+                    println!("focus distance: {:.2}", scene.camera.focus_dist);
+                }
+            }
+            if input.key_held(VirtualKeyCode::Q) {
+                scene.camera.roll(-0.02);
+            }
+            if input.key_held(VirtualKeyCode::E) {
+                scene.camera.roll(0.02);
+            }
+            if input.key_pressed(VirtualKeyCode::O) {
+                scene.camera.projection = scene.camera.projection.next();
+                println!("projection: {:?}", scene.camera.projection);
+            }
+            if input.key_pressed(VirtualKeyCode::RBracket) {
+                scene.camera.aperture = (scene.camera.aperture + 0.05).max(0.05);
+                println!("aperture: {:.2}", scene.camera.aperture);
+            }
+            if input.key_pressed(VirtualKeyCode::LBracket) {
+                scene.camera.aperture = (scene.camera.aperture - 0.05).max(0.0);
+                println!("aperture: {:.2}", scene.camera.aperture);
+            }
 
             // Adjust high DPI factor
             if let Some(factor) = input.scale_factor_changed() {
@@ -129,6 +212,10 @@ fn main() -> Result<(), Error> {
             }
             if !paused || input.key_pressed(VirtualKeyCode::Space) {
                 scene.update();
+                frame.clear();
+            }
+            if scene.camera != camera_before {
+                frame.clear();
             }
             window.request_redraw();
         }
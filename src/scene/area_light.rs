@@ -0,0 +1,150 @@
+use std::f32::consts::PI;
+use glam::{Vec2, Vec3A};
+use crate::geometry::ray::Ray;
+use crate::scene::light::{LightSample, LightSource};
+use crate::scene::sampling::{concentric_disk, orthonormal_basis};
+
+/// minimal distance of a hit from the ray origin
+const EPSILON: f32 = 0.00001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightShape {
+    /// shines in all directions
+    Sphere { center: Vec3A, radius: f32 },
+    /// parallelogram spanned by the edges `u` and `v`, shines to the side of `u x v`
+    Rect { center: Vec3A, u: Vec3A, v: Vec3A },
+    /// shines to the side of `normal`
+    Disk { center: Vec3A, normal: Vec3A, radius: f32 },
+}
+
+/// Light that is emitted by a surface. It casts soft shadows and is visible to camera rays.
+pub struct AreaLight {
+    pub shape: LightShape,
+    /// emitted radiance, the color of the light's surface in the picture
+    pub color: Vec3A,
+    /// number of shadow rays per shaded point
+    pub samples: u32,
+}
+
+impl AreaLight {
+    pub fn create(shape: LightShape, color: Vec3A, samples: u32) -> AreaLight {
+        AreaLight {
+            shape,
+            color,
+            samples: samples.max(1),
+        }
+    }
+
+    /// Distance along the ray to the light if the ray hits its shining side.
+    pub fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let t = match self.shape {
+            LightShape::Sphere { center, radius } => {
+                let l = center - ray.org;
+                let t_ca = l.dot(ray.dir);
+                let d2 = l.length_squared() - t_ca * t_ca;
+                let r2 = radius * radius;
+                if d2 > r2 {
+                    return None;
+                }
+                t_ca - (r2 - d2).sqrt()
+            }
+            LightShape::Rect { center, u, v } => {
+                let normal = u.cross(v);
+                let t = plane_hit(ray, center, normal)?;
+                let d = ray.point_at(t) - center;
+                let a = d.dot(u) / u.length_squared();
+                let b = d.dot(v) / v.length_squared();
+                if a.abs() > 0.5 || b.abs() > 0.5 {
+                    return None;
+                }
+                t
+            }
+            LightShape::Disk { center, normal, radius } => {
+                let t = plane_hit(ray, center, normal)?;
+                if (ray.point_at(t) - center).length_squared() > radius * radius {
+                    return None;
+                }
+                t
+            }
+        };
+        if t > EPSILON {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl LightSource for AreaLight {
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Picks a point on the light for a point of the unit square. `None` if the light faces away from `point`.
+    fn sample(&self, point: Vec3A, u: Vec2) -> Option<LightSample> {
+        let (light_point, normal, area) = match self.shape {
+            LightShape::Sphere { center, radius } => return sample_sphere(center, radius, self.color, point, u),
+            LightShape::Rect { center, u: edge_u, v: edge_v } => {
+                let cross = edge_u.cross(edge_v);
+                let light_point = center + edge_u * (u.x - 0.5) + edge_v * (u.y - 0.5);
+                (light_point, cross.normalize(), cross.length())
+            }
+            LightShape::Disk { center, normal, radius } => {
+                let d = concentric_disk(u) * radius;
+                let (tangent, bitangent) = orthonormal_basis(normal);
+                (center + tangent * d.x + bitangent * d.y, normal, PI * radius * radius)
+            }
+        };
+        let to_light = light_point - point;
+        let dist2 = to_light.length_squared();
+        let dist = dist2.sqrt();
+        let dir = to_light / dist;
+        let cos_light = -normal.dot(dir);
+        if cos_light <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            dir,
+            dist,
+            intensity: self.color * (cos_light * area / dist2),
+        })
+    }
+}
+
+/// Picks a direction uniformly from the cone in which the sphere is seen from `point`, so every sample hits the visible
+/// side of the sphere.
+fn sample_sphere(center: Vec3A, radius: f32, color: Vec3A, point: Vec3A, u: Vec2) -> Option<LightSample> {
+    let to_center = center - point;
+    let dist2 = to_center.length_squared();
+    let r2 = radius * radius;
+    if dist2 <= r2 {
+        // inside the light
+        return None;
+    }
+    let dist = dist2.sqrt();
+    let cos_max = (1.0 - r2 / dist2).max(0.0).sqrt();
+    let cos_theta = 1.0 - u.x * (1.0 - cos_max);
+    let sin2_theta = (1.0 - cos_theta * cos_theta).max(0.0);
+    let sin_theta = sin2_theta.sqrt();
+    let phi = 2.0 * PI * u.y;
+    let axis = to_center / dist;
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    let dir = tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + axis * cos_theta;
+    // distance to the near side of the sphere
+    let t = dist * cos_theta - (r2 - dist2 * sin2_theta).max(0.0).sqrt();
+    let solid_angle = 2.0 * PI * (1.0 - cos_max);
+    Some(LightSample {
+        dir,
+        dist: t,
+        intensity: color * solid_angle,
+    })
+}
+
+/// distance to a plane, only if the ray hits the front side
+fn plane_hit(ray: &Ray, point: Vec3A, normal: Vec3A) -> Option<f32> {
+    let denom = normal.dot(ray.dir);
+    if denom >= 0.0 {
+        return None;
+    }
+    Some((point - ray.org).dot(normal) / denom)
+}
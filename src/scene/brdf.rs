@@ -0,0 +1,162 @@
+use std::f32::consts::PI;
+use glam::Vec3A;
+use rand::Rng;
+use crate::scene::sampling::{cosine_hemisphere, orthonormal_basis};
+
+/// below this roughness the specular lobe is treated as a perfect mirror, GGX gets too spiky to sample
+pub const MIRROR_ROUGHNESS: f32 = 0.03;
+/// reflectance of dielectrics at normal incidence, the value glTF uses
+const DIELECTRIC_F0: f32 = 0.04;
+
+/// The glTF metallic-roughness BRDF at a point: a lambertian base layer under a GGX microfacet specular lobe with
+/// Schlick's Fresnel term and Smith's masking-shadowing function. Metals have no base layer and tint the specular
+/// reflection with the base color.
+pub struct Microfacet {
+    pub base_color: Vec3A,
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+/// A direction picked by `Microfacet::sample`.
+pub struct BrdfSample {
+    pub dir: Vec3A,
+    /// brdf times cosine divided by the probability density of `dir`
+    pub weight: Vec3A,
+    /// the direction is the perfect mirror direction, which `Microfacet::eval` doesn't cover
+    pub mirror: bool,
+}
+
+impl Microfacet {
+    /// color of the lambertian base layer
+    pub fn diffuse_color(&self) -> Vec3A {
+        self.base_color * (1.0 - self.metallic)
+    }
+
+    /// Fresnel reflectance for light arriving at `cos` to the normal
+    pub fn reflectance(&self, cos: f32) -> Vec3A {
+        let f0 = Vec3A::splat(DIELECTRIC_F0).lerp(self.base_color, self.metallic);
+        f0 + (Vec3A::ONE - f0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+    }
+
+    /// smooth enough to reflect like a mirror
+    pub fn is_mirror(&self) -> bool {
+        self.roughness < MIRROR_ROUGHNESS
+    }
+
+    fn alpha(&self) -> f32 {
+        self.roughness * self.roughness
+    }
+
+    /// Light reflected towards `to_viewer` for light from `to_light`, the brdf times the cosine at the light. Mirrors
+    /// only reflect the base layer here, their specular part is a single direction that needs `sample`.
+    pub fn eval(&self, normal: Vec3A, to_viewer: Vec3A, to_light: Vec3A) -> Vec3A {
+        let n_l = normal.dot(to_light);
+        let n_v = normal.dot(to_viewer);
+        if n_l <= 0.0 || n_v <= 0.0 {
+            return Vec3A::ZERO;
+        }
+        if self.is_mirror() {
+            return self.diffuse_color() / PI * (Vec3A::ONE - self.reflectance(n_v)) * n_l;
+        }
+        let half = (to_viewer + to_light).normalize();
+        let fresnel = self.reflectance(to_viewer.dot(half));
+        let alpha = self.alpha();
+        let specular = fresnel * (ggx(normal.dot(half), alpha) * smith(n_v, alpha) * smith(n_l, alpha) / (4.0 * n_v * n_l));
+        let diffuse = self.diffuse_color() / PI * (Vec3A::ONE - fresnel);
+        (diffuse + specular) * n_l
+    }
+
+    /// Picks a direction to continue a path, from the specular lobe or the base layer in proportion to how much light
+    /// they reflect. `None` if the surface reflects nothing towards the viewer.
+    pub fn sample<R: Rng>(&self, normal: Vec3A, to_viewer: Vec3A, rng: &mut R) -> Option<BrdfSample> {
+        let n_v = normal.dot(to_viewer);
+        if n_v <= 0.0 {
+            return None;
+        }
+        let fresnel = self.reflectance(n_v);
+        let specular_weight = average(fresnel);
+        let diffuse_weight = average(self.diffuse_color() * (Vec3A::ONE - fresnel));
+        if specular_weight + diffuse_weight <= 0.0 {
+            return None;
+        }
+        let p_specular = specular_weight / (specular_weight + diffuse_weight);
+        let pick_specular = rng.gen::<f32>() < p_specular;
+
+        if self.is_mirror() {
+            return Some(if pick_specular {
+                BrdfSample {
+                    dir: reflect(-to_viewer, normal),
+                    weight: fresnel / p_specular,
+                    mirror: true,
+                }
+            } else {
+                // lambertian brdf times cosine over the cosine weighted density
+                BrdfSample {
+                    dir: cosine_hemisphere(normal, rng),
+                    weight: self.diffuse_color() * (Vec3A::ONE - fresnel) / (1.0 - p_specular),
+                    mirror: false,
+                }
+            });
+        }
+
+        let alpha = self.alpha();
+        let dir = if pick_specular {
+            reflect(-to_viewer, sample_ggx(normal, alpha, rng))
+        } else {
+            cosine_hemisphere(normal, rng)
+        };
+        let n_l = normal.dot(dir);
+        if n_l <= 0.0 {
+            return None;
+        }
+        // density of picking `dir` through either lobe
+        let half = (to_viewer + dir).normalize();
+        let n_h = normal.dot(half);
+        let pdf_specular = ggx(n_h, alpha) * n_h / (4.0 * to_viewer.dot(half));
+        let pdf = p_specular * pdf_specular + (1.0 - p_specular) * n_l / PI;
+        Some(BrdfSample {
+            dir,
+            weight: self.eval(normal, to_viewer, dir) / pdf,
+            mirror: false,
+        })
+    }
+}
+
+/// Reflects `dir` at a microfacet picked from the GGX distribution around `normal`, which spreads the reflections of
+/// rough surfaces around the mirror direction. `normal` points against `dir`.
+pub fn glossy_reflection<R: Rng>(dir: Vec3A, normal: Vec3A, roughness: f32, rng: &mut R) -> Vec3A {
+    reflect(dir, sample_ggx(normal, roughness * roughness, rng))
+}
+
+/// mirror direction of `dir` at a surface with `normal`
+fn reflect(dir: Vec3A, normal: Vec3A) -> Vec3A {
+    dir - 2.0 * dir.dot(normal) * normal
+}
+
+fn average(color: Vec3A) -> f32 {
+    (color.x + color.y + color.z) / 3.0
+}
+
+/// GGX (Trowbridge-Reitz) distribution of the microfacet normals
+fn ggx(n_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_h * n_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Smith's masking function for GGX, the fraction of microfacets visible at `cos` to the normal
+fn smith(cos: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+}
+
+/// microfacet normal with a density of `ggx(n_h) * n_h`
+fn sample_ggx<R: Rng>(normal: Vec3A, alpha: f32, rng: &mut R) -> Vec3A {
+    let u: f32 = rng.gen();
+    let phi = 2.0 * PI * rng.gen::<f32>();
+    let tan2_theta = alpha * alpha * u / (1.0 - u).max(1e-7);
+    let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + normal * cos_theta
+}
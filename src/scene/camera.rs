@@ -1,11 +1,90 @@
-use glam::{Mat3A, Vec3A, Vec3};
-use crate::geometry::ray::Ray;
+use std::f32::consts::PI;
+use std::str::FromStr;
+use glam::{Mat3, Quat, Vec2, Vec3A, Vec3};
+use serde::Deserialize;
+use crate::geometry::ray::{Ray, RayCone};
+use crate::scene::sampling::{concentric_disk, regular_polygon};
 
+/// Shape of the lens opening, out of focus highlights take this shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bokeh {
+    Disk,
+    /// aperture made of straight blades
+    Polygon { blades: u32 },
+}
+
+impl Bokeh {
+    /// point on the lens for a point of the unit square, within the unit circle
+    pub fn sample(&self, u: Vec2) -> Vec2 {
+        match self {
+            Bokeh::Disk => concentric_disk(u),
+            Bokeh::Polygon { blades } => regular_polygon(*blades, u),
+        }
+    }
+}
+
+/// How the pixels of the screen map to ray directions.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Projection {
+    /// pinhole or thin lens, the field of view follows from `zoom` and `screen_dist`
+    Perspective,
+    /// parallel rays for technical views, `height` is the height of the view in world units
+    Orthographic { height: f32 },
+    /// equidistant fisheye, `fov` is the angle in degrees across the height of the image
+    Fisheye { fov: f32 },
+    /// full 360° x 180° panorama, e.g. for baking environment maps
+    Equirectangular,
+}
+
+impl Projection {
+    /// the next projection with default settings, for cycling through them in the window
+    pub fn next(&self) -> Projection {
+        match self {
+            Projection::Perspective => Projection::Orthographic { height: 20.0 },
+            Projection::Orthographic { .. } => Projection::Fisheye { fov: 180.0 },
+            Projection::Fisheye { .. } => Projection::Equirectangular,
+            Projection::Equirectangular => Projection::Perspective,
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    /// the projections with default settings
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perspective" => Ok(Projection::Perspective),
+            "orthographic" => Ok(Projection::Orthographic { height: 20.0 }),
+            "fisheye" => Ok(Projection::Fisheye { fov: 180.0 }),
+            "equirectangular" => Ok(Projection::Equirectangular),
+            _ => Err(format!(
+                "unknown projection '{}', expected 'perspective', 'orthographic', 'fisheye' or 'equirectangular'",
+                s
+            )),
+        }
+    }
+}
+
+/// the camera never looks closer than this to straight up or down when turned, so the view can't flip over
+const MAX_PITCH: f32 = 89.0 * PI / 180.0;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Camera {
     pub org: Vec3A,
-    pub dir: Vec3A,
+    /// rotation from camera space to world space. In camera space the view direction is +z, up is +y and screen
+    /// right is -x.
+    pub orientation: Quat,
     pub zoom: f32,
     pub screen_dist: f32,
+    /// radius of the lens, 0 is a pinhole camera with everything in focus
+    pub aperture: f32,
+    /// distance from `org` along `dir` of the plane that is in focus
+    pub focus_dist: f32,
+    pub bokeh: Bokeh,
+    /// depth of field only applies to the perspective projection
+    pub projection: Projection,
 }
 
 impl Camera {
@@ -13,16 +92,59 @@ impl Camera {
         self.zoom = self.zoom * change;
     }
 
+    /// view direction
+    pub fn dir(&self) -> Vec3A {
+        self.orientation * Vec3A::Z
+    }
+
+    /// up direction of the screen
+    pub fn up(&self) -> Vec3A {
+        self.orientation * Vec3A::Y
+    }
+
+    /// right direction of the screen
+    pub fn right(&self) -> Vec3A {
+        self.orientation * -Vec3A::X
+    }
+
+    /// Turns the camera towards `target`, keeping the top of the screen on the side of `up`.
+    pub fn look_at(&mut self, target: Vec3A, up: Vec3A) {
+        self.look_in(target - self.org, up);
+    }
+
+    /// Turns the camera to look along `dir`, keeping the top of the screen on the side of `up`. If they are parallel,
+    /// the top of the screen is on the side of the previous view direction, like when tilting a real camera.
+    pub fn look_in(&mut self, dir: Vec3A, up: Vec3A) {
+        let forward = dir.normalize();
+        let right = [up, self.dir(), self.up(), Vec3A::X]
+            .iter()
+            .map(|up| forward.cross(*up))
+            .find(|right| right.length_squared() > 1e-8)
+            .unwrap_or(Vec3A::X)
+            .normalize();
+        let up = right.cross(forward);
+        self.orientation = Quat::from_mat3(&Mat3::from_cols(Vec3::from(-right), Vec3::from(up), Vec3::from(forward))).normalize();
+    }
+
+    /// Rotates the camera around its view direction by `angle` radians, positive angles tilt the top of the screen
+    /// to the right.
+    pub fn roll(&mut self, angle: f32) {
+        self.orientation = (self.orientation * Quat::from_rotation_z(angle)).normalize();
+    }
+
+    /// Turns by a mouse movement in pixels: around the world's y axis for `p0` and up or down for `p1`.
     pub fn turn(&mut self, p0: isize, p1: isize) {
         let inverse_speed = 800.0;
-        let rot_horizontal = Mat3A::from_rotation_y(p0 as f32 / inverse_speed);
-        let rot = Mat3A::from_axis_angle(Vec3::from(self.dir.cross(Vec3A::Y)), p1 as f32 / inverse_speed) * rot_horizontal;
-        self.dir = (rot * self.dir).normalize();
+        let yaw = Quat::from_rotation_y(p0 as f32 / inverse_speed);
+        let pitch = self.dir().y.clamp(-1.0, 1.0).asin();
+        let new_pitch = (pitch + p1 as f32 / inverse_speed).clamp(-MAX_PITCH, MAX_PITCH);
+        // rotating around the camera's x axis tilts the view up or down
+        let tilt = Quat::from_rotation_x(pitch - new_pitch);
+        self.orientation = (yaw * self.orientation * tilt).normalize();
     }
 
     fn go_x(&mut self, dist: f32) {
-        let left = Vec3A::Y.cross(self.dir).normalize();
-        self.org += left * -dist;
+        self.org += self.right() * dist;
     }
 
     pub fn go_left(&mut self) {
@@ -33,32 +155,84 @@ impl Camera {
         self.go_x(1.0);
     }
     pub fn go_forward(&mut self) {
-        self.org += self.dir;
+        self.org += self.dir();
     }
 
     pub fn go_backward(&mut self) {
-        self.org -= self.dir;
+        self.org -= self.dir();
     }
 
-    pub fn render<T: FnMut(&Ray)>(&self, width: i32, height: i32, mut trace: T) {
-        let screen_center = self.org + self.dir * self.screen_dist;
-        let to_left = self.dir.cross(Vec3A::Y).normalize();
-        let to_top = self.dir.cross(to_left).normalize();
+    /// Ray through the point (`x`, `y`) of the screen in pixels, integer coordinates hit the pixel centers.
+    /// `lens` is the point on the lens from `Bokeh::sample`, it only matters if the aperture is open.
+    pub fn primary_ray(&self, width: i32, height: i32, x: f32, y: f32, lens: Vec2) -> Ray {
+        let forward = self.dir();
+        let screen_center = self.org + forward * self.screen_dist;
+        let right = self.right();
+        // rows count downwards
+        let down = -self.up();
 
         let wh = width / 2;
         let hh = height / 2;
+        // offset from the center of the screen in pixels, along right and down
+        let (px, py) = (x - wh as f32, y - hh as f32);
+
+        match self.projection {
+            Projection::Perspective => {}
+            Projection::Orthographic { height: view_height } => {
+                let scale = view_height / height as f32;
+                return Ray { org: self.org + (right * px + down * py) * scale, dir: forward };
+            }
+            Projection::Fisheye { fov } => {
+                let offset = Vec2::new(px, py) / (height as f32 / 2.0);
+                let r = offset.length();
+                let theta = r * fov.to_radians() / 2.0;
+                let side = if r > 0.0 { (right * offset.x + down * offset.y) / r } else { Vec3A::ZERO };
+                let dir = (forward * theta.cos() + side * theta.sin()).normalize();
+                return Ray { org: self.org, dir };
+            }
+            Projection::Equirectangular => {
+                let longitude = px / width as f32 * 2.0 * PI;
+                let latitude = py / height as f32 * PI;
+                let horizontal = forward * longitude.cos() + right * longitude.sin();
+                let dir = (horizontal * latitude.cos() + down * latitude.sin()).normalize();
+                return Ray { org: self.org, dir };
+            }
+        }
 
         let hf = height as f32 / self.zoom;
 
-        for y in -hh..hh {
-            let dy = y as f32 * 2.0 / hf;
-            let top_offset = dy * to_top;
-            for x in -wh..wh {
-                let dx = x as f32 * 2.0 / hf;
-                let sp = screen_center + dx * to_left + top_offset;
-                let dir = (sp - self.org).normalize();
-                trace(&Ray { org: sp, dir });
+        let dx = px * 2.0 / hf;
+        let dy = py * 2.0 / hf;
+        let sp = screen_center + dx * right + dy * down;
+        let dir = (sp - self.org).normalize();
+        if self.aperture <= 0.0 {
+            return Ray { org: sp, dir };
+        }
+
+        // thin lens: all rays through a pixel meet again on the focus plane
+        let focus_point = self.org + dir * (self.focus_dist / dir.dot(forward));
+        let lens_point = self.org + (right * lens.x + down * lens.y) * self.aperture;
+        let dir = (focus_point - lens_point).normalize();
+        // start on the screen like the pinhole rays, so nothing between the camera and the screen shows up
+        Ray { org: lens_point + dir * (self.screen_dist / dir.dot(forward)), dir }
+    }
+
+    /// Cone covering one pixel around the rays of `primary_ray`, ignoring the blur of an open aperture.
+    pub fn pixel_cone(&self, width: i32, height: i32) -> RayCone {
+        match self.projection {
+            Projection::Perspective => {
+                // the rays start on the screen, where a pixel is 2 * zoom / height wide
+                let pixel = 2.0 * self.zoom / height as f32;
+                RayCone { width: pixel, spread: pixel / self.screen_dist }
             }
+            Projection::Orthographic { height: view_height } => RayCone { width: view_height / height as f32, spread: 0.0 },
+            Projection::Fisheye { fov } => RayCone { width: 0.0, spread: fov.to_radians() / height as f32 },
+            Projection::Equirectangular => RayCone { width: 0.0, spread: 2.0 * PI / width as f32 },
         }
     }
+
+    /// Moves the focus plane through `point`.
+    pub fn focus_on(&mut self, point: Vec3A) {
+        self.focus_dist = (point - self.org).dot(self.dir()).max(self.screen_dist);
+    }
 }
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use glam::{Vec2, Vec3A};
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ImageError, RgbImage};
+use crate::scene::texture::srgb_texel;
+
+const RIGHT: usize = 0;
+const LEFT: usize = 1;
+const TOP: usize = 2;
+const BOTTOM: usize = 3;
+const FRONT: usize = 4;
+const BACK: usize = 5;
+
+/// Sky made of six square sRGB images on the faces of a cube around the scene. The front face is seen when looking
+/// along +z like the default camera, the right face on the right of the screen (at -x) and the top face at +y.
+pub struct CubeMap {
+    /// in the order right, left, top, bottom, front, back
+    faces: [Face; 6],
+    /// width and height of every face
+    size: u32,
+}
+
+/// Square part of an image that holds one face, the faces of a cross share its image.
+struct Face {
+    image: Arc<RgbImage>,
+    /// top left corner of the face in the image
+    x: u32,
+    y: u32,
+    /// upside down, like the back face at the bottom of a vertical cross
+    flipped: bool,
+}
+
+impl CubeMap {
+    /// Takes the faces in the order right, left, top, bottom, front, back. Fails unless they are all squares of the
+    /// same size.
+    pub fn create(faces: [Arc<RgbImage>; 6]) -> Result<CubeMap, ImageError> {
+        let size = faces[0].width();
+        if size == 0 || faces.iter().any(|face| face.width() != size || face.height() != size) {
+            return Err(dimension_mismatch());
+        }
+        let faces = faces.map(|image| Face { image, x: 0, y: 0, flipped: false });
+        Ok(CubeMap { faces, size })
+    }
+
+    /// Cuts the faces out of a cross layout. A horizontal cross (4:3) has the left, front, right and back faces in
+    /// its middle row, a vertical cross (3:4) has the back face upside down below the bottom face.
+    pub fn from_cross(cross: Arc<RgbImage>) -> Result<CubeMap, ImageError> {
+        let (width, height) = cross.dimensions();
+        let (size, back) = if width * 3 == height * 4 {
+            (width / 4, (3, 1))
+        } else if width * 4 == height * 3 {
+            (width / 3, (1, 3))
+        } else {
+            return Err(dimension_mismatch());
+        };
+        if size == 0 {
+            return Err(dimension_mismatch());
+        }
+        let face = |(column, row): (u32, u32)| Face {
+            image: cross.clone(),
+            x: column * size,
+            y: row * size,
+            flipped: row == 3,
+        };
+        let faces = [face((2, 1)), face((0, 1)), face((1, 0)), face((1, 2)), face((1, 1)), face(back)];
+        Ok(CubeMap { faces, size })
+    }
+
+    /// Bilinear lookup of the linear color in direction `dir`. Lookups near an edge blend in the texels of the
+    /// neighbouring face, so the edges of the cube don't show.
+    pub fn color(&self, dir: Vec3A) -> Vec3A {
+        let (face, uv) = project(dir);
+        let x = uv.x * self.size as f32 - 0.5;
+        let y = uv.y * self.size as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |dx: i32, dy: i32| self.texel(face, x0 as i32 + dx, y0 as i32 + dy);
+        let top = texel(0, 0) * (1.0 - fx) + texel(1, 0) * fx;
+        let bottom = texel(0, 1) * (1.0 - fx) + texel(1, 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Texel of a face, texels beyond its edges are taken from the face that continues there.
+    fn texel(&self, face: usize, x: i32, y: i32) -> Vec3A {
+        let size = self.size as i32;
+        let (face, x, y) = if (0..size).contains(&x) && (0..size).contains(&y) {
+            (face, x as u32, y as u32)
+        } else {
+            let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) / self.size as f32;
+            let (face, uv) = project(unproject(face, center));
+            let to_texel = |c: f32| ((c * self.size as f32) as u32).min(self.size - 1);
+            (face, to_texel(uv.x), to_texel(uv.y))
+        };
+        let face = &self.faces[face];
+        let (x, y) = if face.flipped { (self.size - 1 - x, self.size - 1 - y) } else { (x, y) };
+        let pixel = face.image.get_pixel(face.x + x, face.y + y);
+        Vec3A::new(srgb_texel(pixel[0]), srgb_texel(pixel[1]), srgb_texel(pixel[2]))
+    }
+}
+
+fn dimension_mismatch() -> ImageError {
+    ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+}
+
+/// Face seen in direction `dir` and the position on it, with the layout of OpenGL cube maps. Their coordinate system
+/// is mirrored compared to the scene, so x is flipped.
+fn project(dir: Vec3A) -> (usize, Vec2) {
+    let (x, y, z) = (-dir.x, dir.y, dir.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    let (face, s, t, major) = if ax >= ay && ax >= az {
+        if x > 0.0 { (RIGHT, -z, -y, ax) } else { (LEFT, z, -y, ax) }
+    } else if ay >= az {
+        if y > 0.0 { (TOP, x, z, ay) } else { (BOTTOM, x, -z, ay) }
+    } else if z > 0.0 {
+        (FRONT, x, -y, az)
+    } else {
+        (BACK, -x, -y, az)
+    };
+    (face, Vec2::new((s / major + 1.0) * 0.5, (t / major + 1.0) * 0.5))
+}
+
+/// direction towards a position on a face, the inverse of `project`. Positions outside [0, 1] continue the plane of
+/// the face.
+fn unproject(face: usize, uv: Vec2) -> Vec3A {
+    let (s, t) = (uv.x * 2.0 - 1.0, uv.y * 2.0 - 1.0);
+    let (x, y, z) = match face {
+        RIGHT => (1.0, -t, -s),
+        LEFT => (-1.0, -t, s),
+        TOP => (s, 1.0, t),
+        BOTTOM => (s, -1.0, -t),
+        FRONT => (s, -t, 1.0),
+        _ => (-s, -t, -1.0),
+    };
+    Vec3A::new(-x, y, z)
+}
@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+use std::path::Path;
+use glam::{Vec2, Vec3A};
+use image::{DynamicImage, ImageError, Rgb32FImage};
+use crate::color::srgb_to_linear;
+use crate::scene::light::{LightSample, LightSource};
+
+/// HDR equirectangular image of the surroundings. It is shown where rays miss all objects and lights the scene,
+/// the bright parts of the image get most of the shadow rays.
+pub struct EnvironmentMap {
+    /// linear radiance, the top row looks straight up and the center looks along +z
+    image: Rgb32FImage,
+    /// rotation around the y axis in radians
+    pub rotation: f32,
+    /// multiplies the radiance of the image
+    pub intensity: f32,
+    /// number of shadow rays per shaded point
+    pub samples: u32,
+    /// probabilities of the rows, for picking the row of a shadow ray
+    rows: Distribution,
+    /// probabilities of the pixels in every row
+    columns: Vec<Distribution>,
+}
+
+impl EnvironmentMap {
+    pub fn create(image: Rgb32FImage, rotation: f32, intensity: f32, samples: u32) -> EnvironmentMap {
+        let (width, height) = image.dimensions();
+        let luminance = |x: u32, y: u32| {
+            let [r, g, b] = image.get_pixel(x, y).0;
+            0.2126 * r + 0.7152 * g + 0.0722 * b
+        };
+        let columns: Vec<Distribution> = (0..height)
+            .map(|y| {
+                // rows near the poles cover less solid angle
+                let sin_theta = ((y as f32 + 0.5) / height as f32 * PI).sin();
+                let weights = (0..width).map(|x| {
+                    // bilinear filtering blends the neighbours into a pixel, the brightest of them bounds what a
+                    // lookup returns there
+                    let mut brightest = 0.0f32;
+                    for dy in [-1, 0, 1] {
+                        for dx in [-1, 0, 1] {
+                            let nx = (x as i64 + dx).rem_euclid(width as i64) as u32;
+                            let ny = (y as i64 + dy).clamp(0, height as i64 - 1) as u32;
+                            brightest = brightest.max(luminance(nx, ny));
+                        }
+                    }
+                    brightest * sin_theta
+                });
+                Distribution::create(weights.collect())
+            })
+            .collect();
+        let rows = Distribution::create(columns.iter().map(|row| row.total).collect());
+        EnvironmentMap {
+            image,
+            rotation,
+            intensity,
+            samples: samples.max(1),
+            rows,
+            columns,
+        }
+    }
+
+    /// Loads an `.hdr` or `.exr` file. 8 bit images like `.jpg` are taken as sRGB and converted to linear colors.
+    pub fn load<P: AsRef<Path>>(path: P, rotation: f32, intensity: f32, samples: u32) -> Result<EnvironmentMap, ImageError> {
+        let image = image::open(path)?;
+        let linear = matches!(image, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_));
+        let mut image = image.into_rgb32f();
+        if !linear {
+            image.pixels_mut().flat_map(|p| p.0.iter_mut()).for_each(|c| *c = srgb_to_linear(*c));
+        }
+        Ok(EnvironmentMap::create(image, rotation, intensity, samples))
+    }
+
+    /// bytes taken by the image and the tables for picking the directions of shadow rays
+    pub fn memory(&self) -> usize {
+        let tables = self.rows.cdf.len() + self.columns.iter().map(|row| row.cdf.len()).sum::<usize>();
+        (self.image.as_raw().len() + tables) * std::mem::size_of::<f32>()
+    }
+
+    /// radiance arriving from direction `dir`
+    pub fn radiance(&self, dir: Vec3A) -> Vec3A {
+        let uv = self.dir_to_uv(dir);
+        self.lookup(uv) * self.intensity
+    }
+
+    /// position in the image for a direction, both coordinates in [0, 1]
+    fn dir_to_uv(&self, dir: Vec3A) -> Vec2 {
+        let dir = rotate_y(dir, -self.rotation);
+        let u = dir.x.atan2(dir.z) / (2.0 * PI) + 0.5;
+        let v = dir.y.clamp(-1.0, 1.0).acos() / PI;
+        Vec2::new(u, v)
+    }
+
+    fn uv_to_dir(&self, uv: Vec2) -> Vec3A {
+        let phi = (uv.x - 0.5) * 2.0 * PI;
+        let theta = uv.y * PI;
+        let dir = Vec3A::new(theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos());
+        rotate_y(dir, self.rotation)
+    }
+
+    /// bilinear lookup, wraps around horizontally
+    fn lookup(&self, uv: Vec2) -> Vec3A {
+        let (width, height) = self.image.dimensions();
+        let x = uv.x * width as f32 - 0.5;
+        let y = (uv.y * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |x: f32, y: f32| {
+            let x = (x as i64).rem_euclid(width as i64) as u32;
+            let y = (y as u32).min(height - 1);
+            Vec3A::from(self.image.get_pixel(x, y).0)
+        };
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1.0, y0) * fx;
+        let bottom = texel(x0, y0 + 1.0) * (1.0 - fx) + texel(x0 + 1.0, y0 + 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+impl LightSource for EnvironmentMap {
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Picks a direction with a probability proportional to the brightness of the image.
+    fn sample(&self, _point: Vec3A, u: Vec2) -> Option<LightSample> {
+        if self.rows.total <= 0.0 {
+            return None;
+        }
+        let (width, height) = self.image.dimensions();
+        let (y, v) = self.rows.sample(u.y);
+        let (x, u) = self.columns[y].sample(u.x);
+        let uv = Vec2::new((x as f32 + u) / width as f32, (y as f32 + v) / height as f32);
+        let sin_theta = (uv.y * PI).sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+        // the density is constant within a pixel of the image, the mapping to directions stretches it by 2pi * pi
+        // and squeezes it towards the poles
+        let pdf_uv = self.rows.probability(y) * self.columns[y].probability(x) * (width * height) as f32;
+        let pdf = pdf_uv / (2.0 * PI * PI * sin_theta);
+        Some(LightSample {
+            dir: self.uv_to_dir(uv),
+            dist: f32::INFINITY,
+            intensity: self.lookup(uv) * self.intensity / pdf,
+        })
+    }
+}
+
+fn rotate_y(dir: Vec3A, angle: f32) -> Vec3A {
+    let (sin, cos) = angle.sin_cos();
+    Vec3A::new(cos * dir.x + sin * dir.z, dir.y, -sin * dir.x + cos * dir.z)
+}
+
+/// Picks indices with probabilities proportional to their weights.
+struct Distribution {
+    /// running sum of the normalized weights, starts with 0 and ends with 1
+    cdf: Vec<f32>,
+    total: f32,
+}
+
+impl Distribution {
+    fn create(weights: Vec<f32>) -> Distribution {
+        let total: f32 = weights.iter().sum();
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        let mut sum = 0.0;
+        cdf.push(0.0);
+        for weight in weights.iter() {
+            sum += if total > 0.0 { weight / total } else { 1.0 / weights.len() as f32 };
+            cdf.push(sum);
+        }
+        Distribution { cdf, total }
+    }
+
+    fn probability(&self, index: usize) -> f32 {
+        self.cdf[index + 1] - self.cdf[index]
+    }
+
+    /// index for a uniform `u` in [0, 1), plus where `u` lies within the interval of the index
+    fn sample(&self, u: f32) -> (usize, f32) {
+        let count = self.cdf.len() - 1;
+        // last entry of the cdf that is <= u
+        let index = (self.cdf.partition_point(|c| *c <= u) - 1).min(count - 1);
+        let probability = self.probability(index);
+        let offset = if probability > 0.0 { (u - self.cdf[index]) / probability } else { 0.5 };
+        (index, offset.clamp(0.0, 0.99999))
+    }
+}
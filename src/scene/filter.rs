@@ -0,0 +1,84 @@
+use std::str::FromStr;
+use serde::Deserialize;
+
+/// Reconstruction filter that weights the samples of a pixel by their distance to the pixel center.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    /// every sample inside the pixel counts the same
+    Box,
+    /// linear falloff, reaches into the neighbour pixels
+    Tent,
+    /// smooth, slightly blurry
+    Gaussian,
+    /// Mitchell-Netravali with B = C = 1/3, sharper than the gaussian thanks to its negative lobes
+    Mitchell,
+}
+
+impl Filter {
+    /// half the width of the area the samples of a pixel are taken from, in pixels
+    pub fn radius(&self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+        }
+    }
+
+    /// weight of a sample at offset (`x`, `y`) from the pixel center, the filters are separable
+    pub fn weight(&self, x: f32, y: f32) -> f32 {
+        self.weight_1d(x) * self.weight_1d(y)
+    }
+
+    fn weight_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        let radius = self.radius();
+        if x > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - x / radius,
+            Filter::Gaussian => {
+                // sigma of half a pixel, shifted so the weight reaches 0 at the radius
+                let gaussian = |x: f32| (-2.0 * x * x).exp();
+                gaussian(x) - gaussian(radius)
+            }
+            Filter::Mitchell => {
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                let (x2, x3) = (x * x, x * x * x);
+                if x < 1.0 {
+                    ((12.0 - 9.0 * B - 6.0 * C) * x3 + (-18.0 + 12.0 * B + 6.0 * C) * x2 + (6.0 - 2.0 * B)) / 6.0
+                } else {
+                    ((-B - 6.0 * C) * x3 + (6.0 * B + 30.0 * C) * x2 + (-12.0 * B - 48.0 * C) * x + (8.0 * B + 24.0 * C)) / 6.0
+                }
+            }
+        }
+    }
+
+    /// the next filter, for cycling through them in the window
+    pub fn next(&self) -> Filter {
+        match self {
+            Filter::Box => Filter::Tent,
+            Filter::Tent => Filter::Gaussian,
+            Filter::Gaussian => Filter::Mitchell,
+            Filter::Mitchell => Filter::Box,
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(Filter::Box),
+            "tent" => Ok(Filter::Tent),
+            "gaussian" => Ok(Filter::Gaussian),
+            "mitchell" => Ok(Filter::Mitchell),
+            _ => Err(format!("unknown filter '{}', expected 'box', 'tent', 'gaussian' or 'mitchell'", s)),
+        }
+    }
+}
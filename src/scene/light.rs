@@ -1,12 +1,115 @@
-use glam::Vec3A;
+use glam::{Vec2, Vec3A};
 
-pub struct Light {
+/// Light arriving at a point in the scene from one point of a light source.
+pub struct LightSample {
+    /// unit vector from the shaded point towards the light
+    pub dir: Vec3A,
+    /// distance to the light, shadow rays stop there. Infinite for directional lights.
+    pub dist: f32,
+    /// irradiance the sample delivers to a surface facing it, for a single sample
+    pub intensity: Vec3A,
+}
+
+/// Anything that lights the scene. The shaders ask every light source for `samples` samples per shaded point and
+/// average them, each one is checked with a shadow ray.
+pub trait LightSource: Send + Sync {
+    /// number of shadow rays per shaded point, more than one for lights that cast soft shadows
+    fn samples(&self) -> u32 {
+        1
+    }
+
+    /// Light reaching `point`, `u` is a point of the unit square that picks the point on the light. `None` if no light
+    /// arrives at all.
+    fn sample(&self, point: Vec3A, u: Vec2) -> Option<LightSample>;
+}
+
+/// Light from far away that arrives everywhere from the same direction, like the sun. Its shadows are sharp.
+pub struct DirectionalLight {
+    /// direction the light travels in
+    pub dir: Vec3A,
+    /// irradiance on a surface facing the light
+    pub color: Vec3A,
+}
+
+impl LightSource for DirectionalLight {
+    fn sample(&self, _point: Vec3A, _u: Vec2) -> Option<LightSample> {
+        Some(LightSample {
+            dir: -self.dir,
+            dist: f32::INFINITY,
+            intensity: self.color,
+        })
+    }
+}
+
+/// Light shining from a single point in all directions, it falls off with the square of the distance.
+pub struct PointLight {
+    pub org: Vec3A,
+    /// radiant intensity, the irradiance on a surface facing the light at a distance of 1
+    pub color: Vec3A,
+}
+
+impl LightSource for PointLight {
+    fn sample(&self, point: Vec3A, _u: Vec2) -> Option<LightSample> {
+        point_sample(self.org, self.color, point)
+    }
+}
+
+/// Point light that only shines into a cone around `dir`. It has full intensity up to `inner_angle` from `dir` and
+/// fades out smoothly to `outer_angle`.
+pub struct SpotLight {
     pub org: Vec3A,
     pub dir: Vec3A,
-    /**
-    between 0 and 1. 1: only a point
-     */
-    pub direction_sensitivity: f32,
+    /// in radians
+    pub inner_angle: f32,
+    /// in radians
+    pub outer_angle: f32,
+    /// radiant intensity in the direction of `dir`
     pub color: Vec3A,
 }
 
+impl SpotLight {
+    /// fraction of the intensity that is sent in direction `dir_from_light`
+    pub fn cone_factor(&self, dir_from_light: Vec3A) -> f32 {
+        let cos = self.dir.dot(dir_from_light);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos >= cos_inner {
+            return 1.0;
+        }
+        if cos <= cos_outer {
+            return 0.0;
+        }
+        let x = (cos - cos_outer) / (cos_inner - cos_outer);
+        // smoothstep
+        x * x * (3.0 - 2.0 * x)
+    }
+}
+
+impl LightSource for SpotLight {
+    fn sample(&self, point: Vec3A, _u: Vec2) -> Option<LightSample> {
+        let sample = point_sample(self.org, self.color, point)?;
+        let factor = self.cone_factor(-sample.dir);
+        if factor <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            intensity: sample.intensity * factor,
+            ..sample
+        })
+    }
+}
+
+/// inverse square falloff from a point light at `org`
+fn point_sample(org: Vec3A, color: Vec3A, point: Vec3A) -> Option<LightSample> {
+    let to_light = org - point;
+    let dist2 = to_light.length_squared();
+    if dist2 <= 0.0 {
+        return None;
+    }
+    let dist = dist2.sqrt();
+    Some(LightSample {
+        dir: to_light / dist,
+        dist,
+        intensity: color / dist2,
+    })
+}
@@ -1,22 +1,126 @@
-use std::rc::Rc;
-use glam::Vec3A;
-use image::RgbImage;
+use std::sync::Arc;
+use glam::{Vec2, Vec3A};
+use serde::Deserialize;
+use crate::scene::mip_map::Footprint;
+use crate::scene::texture::Texture;
+
+/// How light is reflected at the surface of a material.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shading {
+    /// diffuse with a fixed highlight, plus a mirror reflection weighted by `reflect`
+    Classic,
+    /// glTF style metallic-roughness model with a GGX microfacet specular lobe, `reflect` is not used
+    MetallicRoughness,
+}
+
+/// Direction of the green channel of tangent space normal maps, the red channel points along u in both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum NormalMapFormat {
+    /// green points up in the image, like glTF and Blender
+    #[serde(rename = "opengl")]
+    OpenGl,
+    /// green points down in the image, like Unreal and 3ds Max
+    #[serde(rename = "directx")]
+    DirectX,
+}
 
 pub struct Material {
     pub color: Vec3A,
     pub reflect: f32,
+    /// index of refraction, only used if the material is transparent
+    pub ior: f32,
+    /**
+    between 0 and 1. 0: opaque, 1: all light that isn't reflected by the Fresnel term passes through
+     */
+    pub transparency: f32,
+
+    pub texture: Option<Box<Texture>>,
+    /// Tangent space normals in an image. Procedural textures are heights instead, the slope of the pattern tilts
+    /// the normal.
+    pub normal_map: Option<Box<Texture>>,
+    pub normal_map_format: NormalMapFormat,
+    /// heights in the brightness of a texture, an alternative to normal maps, its slope tilts the normal
+    pub bump_map: Option<Box<Texture>>,
+    /// height of the bumps of a bump map or a procedural normal map between its values 0 and 1, in scene units
+    pub bump_depth: f32,
 
-    pub texture: Option<Box<RgbImage>>,
-    pub normal_map: Option<Box<RgbImage>>,
+    pub shading: Shading,
+    /// 0: dielectric, 1: metal
+    pub metallic: f32,
+    /// 0: smooth, 1: rough
+    pub roughness: f32,
+    /// The maps multiply the values above. They are read from the channels of a glTF metallic-roughness texture,
+    /// blue for metallic and green for roughness, so grayscale maps work as well.
+    pub metallic_map: Option<Box<Texture>>,
+    pub roughness_map: Option<Box<Texture>>,
+    /// baked ambient occlusion in the red channel like in glTF, darkens the ambient light and the reflections
+    pub occlusion_map: Option<Box<Texture>>,
+
+    /// light the surface gives off by itself, it stays bright in shadow and shows up in reflections
+    pub emissive: Vec3A,
+    /// multiplies `emissive`, for glowing surfaces brighter than white
+    pub emissive_intensity: f32,
+    /// sRGB texture that is multiplied with `emissive`
+    pub emissive_map: Option<Box<Texture>>,
 }
 
 impl Material {
-    pub fn create(color: Vec3A, reflect: f32) -> Rc<Material> {
-        Rc::new(Material {
+    pub fn create(color: Vec3A, reflect: f32) -> Arc<Material> {
+        Arc::new(Material {
             color,
             reflect,
-            normal_map: None,
-            texture: None,
+            ..Default::default()
         })
     }
+
+    /// light given off at the texture coordinate `tex_coord` and the position `object_coord` relative to the object,
+    /// the emissive map is averaged over `footprint`
+    pub fn emitted(&self, tex_coord: &Vec2, object_coord: &Vec3A, footprint: &Footprint) -> Vec3A {
+        let emissive = self.emissive * self.emissive_intensity;
+        match &self.emissive_map {
+            Some(map) if emissive != Vec3A::ZERO => emissive * map.get_color(tex_coord, object_coord, footprint),
+            _ => emissive,
+        }
+    }
+
+    /// true if one of the maps reads an image, only those are filtered over the footprint of the ray
+    pub fn has_image_maps(&self) -> bool {
+        [
+            &self.texture,
+            &self.normal_map,
+            &self.bump_map,
+            &self.metallic_map,
+            &self.roughness_map,
+            &self.occlusion_map,
+            &self.emissive_map,
+        ]
+        .iter()
+        .any(|map| map.as_ref().is_some_and(|map| !map.is_procedural()))
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            color: Vec3A::ONE,
+            reflect: 0.0,
+            ior: 1.5,
+            transparency: 0.0,
+            texture: None,
+            normal_map: None,
+            normal_map_format: NormalMapFormat::OpenGl,
+            bump_map: None,
+            bump_depth: 0.05,
+            shading: Shading::Classic,
+            metallic: 0.0,
+            roughness: 0.0,
+            metallic_map: None,
+            roughness_map: None,
+            occlusion_map: None,
+            emissive: Vec3A::ZERO,
+            emissive_intensity: 1.0,
+            emissive_map: None,
+        }
+    }
 }
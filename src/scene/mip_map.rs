@@ -0,0 +1,109 @@
+use glam::{Vec2, Vec3A};
+use image::{Rgb, RgbImage};
+use crate::color::linear_to_srgb;
+use crate::scene::texture::srgb_texel;
+
+/// Area a pixel covers on a surface in texture coordinates, the parallelogram spanned by `dx` and `dy` around the
+/// lookup position. The default is a single point, which gives plain bilinear lookups.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Footprint {
+    pub dx: Vec2,
+    pub dy: Vec2,
+    /// Footprints up to this many times longer than wide are covered with several lookups along the long side.
+    /// 1 (or 0) gives plain trilinear filtering, which blurs long footprints across their short side.
+    pub max_anisotropy: u32,
+}
+
+/// Image with a pyramid of copies of half the size down to a single pixel. Lookups pick the copies whose texels
+/// match the footprint of the pixel, so far away textures don't shimmer.
+pub struct MipMap {
+    /// full size first
+    levels: Vec<RgbImage>,
+}
+
+impl MipMap {
+    /// Builds the pyramid, `srgb` color textures are averaged in linear space so they don't darken in the distance.
+    pub fn create(image: RgbImage, srgb: bool) -> MipMap {
+        let mut levels = vec![image];
+        loop {
+            let last = levels.last().unwrap();
+            if last.width() <= 1 && last.height() <= 1 {
+                break;
+            }
+            let next = downsize(last, srgb);
+            levels.push(next);
+        }
+        MipMap { levels }
+    }
+
+    /// number of sizes, the full size included
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// bytes taken by all levels
+    pub fn memory(&self) -> usize {
+        self.levels.iter().map(|level| level.as_raw().len()).sum()
+    }
+
+    /// Average of `lookup` over `footprint`, `lookup` reads a single level at a position.
+    pub fn sample<F: Fn(&RgbImage, Vec2) -> Vec3A>(&self, pos: Vec2, footprint: &Footprint, lookup: F) -> Vec3A {
+        let size = Vec2::new(self.levels[0].width() as f32, self.levels[0].height() as f32);
+        // sides of the footprint in texels
+        let (mut major, mut minor) = (footprint.dx * size, footprint.dy * size);
+        if major.length_squared() < minor.length_squared() {
+            std::mem::swap(&mut major, &mut minor);
+        }
+        let (major_length, minor_length) = (major.length(), minor.length());
+        let max_anisotropy = footprint.max_anisotropy.max(1) as f32;
+        let taps = (major_length / minor_length.max(1e-6)).min(max_anisotropy).ceil().max(1.0) as u32;
+        // each lookup covers a part of the long side, at least as wide as the short side
+        let width = (major_length / taps as f32).max(minor_length);
+        let level = width.max(1.0).log2();
+
+        let step = major / size / taps as f32;
+        let mut color = Vec3A::ZERO;
+        for i in 0..taps {
+            let offset = step * (i as f32 + 0.5 - taps as f32 * 0.5);
+            color += self.trilinear(pos + offset, level, &lookup);
+        }
+        color / taps as f32
+    }
+
+    /// blend of the lookups in the two levels around `level`
+    fn trilinear<F: Fn(&RgbImage, Vec2) -> Vec3A>(&self, pos: Vec2, level: f32, lookup: &F) -> Vec3A {
+        let last = self.levels.len() - 1;
+        let level = level.min(last as f32);
+        let lower = level.floor() as usize;
+        let fraction = level - lower as f32;
+        let color = lookup(&self.levels[lower], pos);
+        if fraction <= 0.0 || lower == last {
+            return color;
+        }
+        color * (1.0 - fraction) + lookup(&self.levels[lower + 1], pos) * fraction
+    }
+}
+
+/// half the size, each texel is the average of up to 2x2 texels
+fn downsize(image: &RgbImage, srgb: bool) -> RgbImage {
+    let (width, height) = image.dimensions();
+    let decode = |c: u8| if srgb { srgb_texel(c) } else { c as f32 / 255.0 };
+    let encode = |c: f32| {
+        let c = if srgb { linear_to_srgb(c) } else { c };
+        (c * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+    RgbImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+        let mut sum = [0.0; 3];
+        let xs = [2 * x, (2 * x + 1).min(width - 1)];
+        let ys = [2 * y, (2 * y + 1).min(height - 1)];
+        for sy in ys {
+            for sx in xs {
+                let pixel = image.get_pixel(sx, sy);
+                for (sum, c) in sum.iter_mut().zip(pixel.0) {
+                    *sum += decode(c) * 0.25;
+                }
+            }
+        }
+        Rgb(sum.map(encode))
+    })
+}
@@ -1,5 +1,18 @@
+pub mod area_light;
+pub mod brdf;
 pub mod camera;
+pub mod cube_map;
+pub mod environment;
+pub mod filter;
 pub mod material;
+pub mod mip_map;
 pub mod texture;
+pub mod texture_cache;
 pub mod light;
-pub mod scene;
\ No newline at end of file
+pub mod obj_loader;
+pub mod optics;
+pub mod path_tracer;
+pub mod procedural;
+pub mod sampling;
+pub mod scene;
+pub mod scene_file;
\ No newline at end of file
@@ -0,0 +1,185 @@
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use glam::{Affine3A, Vec2, Vec3A};
+use image::ImageError;
+
+use crate::geometry::triangle::Triangle;
+use crate::scene::material::{Material, Shading};
+use crate::scene::texture_cache::{TextureCache, TextureLoadError};
+
+/// Error returned by `load_obj`.
+#[derive(Debug)]
+pub enum ObjLoadError {
+    /// the OBJ file or its MTL library could not be read or parsed
+    Obj { path: PathBuf, source: tobj::LoadError },
+    /// a texture referenced by the MTL library could not be loaded
+    Texture { path: PathBuf, source: ImageError },
+}
+
+impl fmt::Display for ObjLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjLoadError::Obj { path, source } => write!(f, "could not load mesh {}: {}", path.display(), source),
+            ObjLoadError::Texture { path, source } => write!(f, "could not load texture {}: {}", path.display(), source),
+        }
+    }
+}
+
+impl From<TextureLoadError> for ObjLoadError {
+    fn from(err: TextureLoadError) -> Self {
+        ObjLoadError::Texture {
+            path: err.path,
+            source: err.source,
+        }
+    }
+}
+
+impl Error for ObjLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjLoadError::Obj { source, .. } => Some(source),
+            ObjLoadError::Texture { source, .. } => Some(source),
+        }
+    }
+}
+
+/**
+Loads the triangles of a Wavefront OBJ file and places them with `transform`.
+
+The MTL diffuse color (`Kd`), dissolve (`d`), index of refraction (`Ni`), diffuse texture (`map_Kd`) and normal map
+(`map_Bump`/`bump`) are mapped onto
+`Material`; texture paths are relative to the OBJ file and loaded through `textures`. Faces without a material get
+`default_mat`. The tangents of the faces around a vertex are averaged like MikkTSpace does, so normal maps stay smooth
+across the edges of smooth meshes.
+ */
+pub fn load_obj<P: AsRef<Path>>(path: P, textures: &mut TextureCache, transform: &Affine3A, default_mat: Arc<Material>) -> Result<Vec<Triangle>, ObjLoadError> {
+    let path = path.as_ref();
+    let obj_error = |source| ObjLoadError::Obj {
+        path: path.to_path_buf(),
+        source,
+    };
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+    let (models, materials) = tobj::load_obj(path, &options).map_err(obj_error)?;
+    let materials = materials.map_err(obj_error)?;
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut load = |file: &str, srgb: bool| textures.texture(base_dir.join(file), srgb).map(Box::new);
+    let mut mats = Vec::with_capacity(materials.len());
+    for mat in materials.iter() {
+        let texture = match mat.diffuse_texture.as_str() {
+            "" => None,
+            file => Some(load(file, true)?),
+        };
+        let normal_map = match mat.normal_texture.as_str() {
+            "" => None,
+            file => Some(load(file, false)?),
+        };
+        // emission and PBR aren't part of the original MTL spec, tobj keeps their extensions as unknown params
+        let mut load_param = |key: &str, srgb: bool| match mat.unknown_param.get(key) {
+            Some(file) => load(file.trim(), srgb).map(Some),
+            None => Ok(None),
+        };
+        let value_param = |key: &str| mat.unknown_param.get(key).and_then(|value| value.trim().parse::<f32>().ok());
+        let emissive_map = load_param("map_Ke", true)?;
+        let emissive = match mat.unknown_param.get("Ke").and_then(|ke| parse_color(ke)) {
+            Some(ke) => ke,
+            None if emissive_map.is_some() => Vec3A::ONE,
+            None => Vec3A::ZERO,
+        };
+        let metallic_map = load_param("map_Pm", false)?;
+        let roughness_map = load_param("map_Pr", false)?;
+        let (metallic, roughness) = (value_param("Pm"), value_param("Pr"));
+        let pbr = metallic.is_some() || roughness.is_some() || metallic_map.is_some() || roughness_map.is_some();
+        mats.push(Arc::new(Material {
+            color: Vec3A::from(mat.diffuse),
+            reflect: 0.0,
+            ior: mat.optical_density,
+            transparency: 1.0 - mat.dissolve,
+            texture,
+            normal_map,
+            shading: if pbr { Shading::MetallicRoughness } else { Shading::Classic },
+            // the maps alone give the values
+            metallic: metallic.unwrap_or(if metallic_map.is_some() { 1.0 } else { 0.0 }),
+            roughness: roughness.unwrap_or(if roughness_map.is_some() { 1.0 } else { 0.0 }),
+            metallic_map,
+            roughness_map,
+            emissive,
+            emissive_map,
+            ..Default::default()
+        }));
+    }
+
+    // normals are transformed with the inverse transpose to stay perpendicular under non-uniform scaling
+    let normal_transform = transform.matrix3.inverse().transpose();
+    let mut triangles = Vec::new();
+    for model in models.iter() {
+        let mesh = &model.mesh;
+        let mat = match mesh.material_id {
+            Some(id) if id < mats.len() => mats[id].clone(),
+            _ => default_mat.clone(),
+        };
+        let position = |i: usize| transform.transform_point3a(Vec3A::new(mesh.positions[i * 3], mesh.positions[i * 3 + 1], mesh.positions[i * 3 + 2]));
+        let normal = |i: usize| (normal_transform * Vec3A::new(mesh.normals[i * 3], mesh.normals[i * 3 + 1], mesh.normals[i * 3 + 2])).normalize();
+        // OBJ has v = 0 at the bottom of the texture, images start at the top
+        let uv = |i: usize| Vec2::new(mesh.texcoords[i * 2], 1.0 - mesh.texcoords[i * 2 + 1]);
+
+        let first = triangles.len();
+        // vertex indices of the triangles of this mesh
+        let mut corners = Vec::new();
+        for face in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [face[0] as usize, face[1] as usize, face[2] as usize];
+            let vertices = [position(a), position(b), position(c)];
+            if (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]).length_squared() == 0.0 {
+                // degenerate triangles can't be hit and have no normal
+                continue;
+            }
+            let normals = if mesh.normals.is_empty() {
+                None
+            } else {
+                Some([normal(a), normal(b), normal(c)])
+            };
+            let uvs = if mesh.texcoords.is_empty() {
+                None
+            } else {
+                Some([uv(a), uv(b), uv(c)])
+            };
+            triangles.push(Triangle::create(vertices, normals, uvs, mat.clone()));
+            corners.push([a, b, c]);
+        }
+
+        if !mesh.texcoords.is_empty() {
+            // vertices on a texture seam are separate vertices, only faces with the same mapping are averaged
+            let mut sums = vec![(Vec3A::ZERO, Vec3A::ZERO); mesh.positions.len() / 3];
+            for (triangle, corners) in triangles[first..].iter().zip(corners.iter()) {
+                let (du, dv) = triangle.uv_derivatives();
+                for &i in corners {
+                    sums[i].0 += du.normalize_or_zero();
+                    sums[i].1 += dv.normalize_or_zero();
+                }
+            }
+            for (triangle, corners) in triangles[first..].iter_mut().zip(corners.iter()) {
+                triangle.vertex_tangents = Some(corners.map(|i| sums[i]));
+            }
+        }
+    }
+    Ok(triangles)
+}
+
+/// three floats separated by whitespace like `0.5 0.2 1.0`
+fn parse_color(s: &str) -> Option<Vec3A> {
+    let values = s
+        .split_whitespace()
+        .map(|v| v.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match values[..] {
+        [r, g, b] => Some(Vec3A::new(r, g, b)),
+        _ => None,
+    }
+}
@@ -0,0 +1,30 @@
+use glam::Vec3A;
+
+/// Refracts `dir` at a surface with `normal` pointing against `dir`. `eta` is n1 / n2.
+/// Returns `None` on total internal reflection.
+pub fn refract(dir: Vec3A, normal: Vec3A, eta: f32) -> Option<Vec3A> {
+    let cos_i = -dir.dot(normal);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    Some((eta * dir + (eta * cos_i - (1.0 - sin2_t).sqrt()) * normal).normalize())
+}
+
+/// Schlick's approximation of the reflected fraction of light going from a medium with refraction index `n1`
+/// into one with `n2`. `cos_i` is the cosine of the angle of incidence.
+pub fn schlick(cos_i: f32, n1: f32, n2: f32) -> f32 {
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    let mut cos = cos_i.max(0.0);
+    if n1 > n2 {
+        // the angle on the side of the thinner medium is the one that counts
+        let eta = n1 / n2;
+        let sin2_t = eta * eta * (1.0 - cos * cos);
+        if sin2_t > 1.0 {
+            // total internal reflection
+            return 1.0;
+        }
+        cos = (1.0 - sin2_t).sqrt();
+    }
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
@@ -0,0 +1,141 @@
+use std::f32::consts::PI;
+use glam::Vec3A;
+use rand::Rng;
+use crate::geometry::ray::{Ray, RayCone};
+use crate::scene::optics::{refract, schlick};
+use crate::scene::material::Shading;
+use crate::scene::sampling::cosine_hemisphere;
+use crate::scene::scene::{offset_origin, Scene, SurfaceHit};
+
+/// number of bounces before paths may be terminated by russian roulette
+const MIN_BOUNCES: i32 = 3;
+
+impl Scene {
+    /// Follows a single random path through the scene and returns the light it carries back along `ray`.
+    ///
+    /// Diffuse bounces pick a cosine weighted direction and light the point directly with shadow rays towards the
+    /// lights (next event estimation). Mirrors and glass are followed with the probability of their share of the
+    /// material, so one path always carries exactly one lobe, rough mirrors spread it around the mirror direction.
+    /// Metallic-roughness materials are lit the same way and continue in a direction sampled from their brdf. Area
+    /// lights that the path runs into only count after camera rays and mirror or glass bounces, after the other
+    /// bounces they are already part of the direct light.
+    /// Emissive materials aren't sampled as lights, so their light counts on every hit.
+    pub(crate) fn trace_path<R: Rng>(&self, ray: &Ray, cone: RayCone, rng: &mut R) -> Vec3A {
+        let mut radiance = Vec3A::ZERO;
+        let mut throughput = Vec3A::ONE;
+        let mut ray = Ray { org: ray.org, dir: ray.dir };
+        let mut cone = cone;
+        let mut specular = true;
+
+        for bounce in 0..self.max_depth {
+            let collision = self.find_collision(&ray);
+            if let Some(emitted) = self.hit_area_light(&ray, collision.map_or(f32::MAX, |(t, _)| t)) {
+                if specular {
+                    radiance += throughput * emitted;
+                }
+                break;
+            }
+            let (t, collision_obj) = match collision {
+                Some(hit) => hit,
+                None => {
+                    if specular || !self.sky_is_light() {
+                        radiance += throughput * self.sky_color(ray.dir);
+                    }
+                    break;
+                }
+            };
+            let hit = self.surface_hit(&ray, cone, t, collision_obj);
+            cone = cone.advance(t);
+            let mat = &hit.mat;
+            radiance += throughput * hit.emitted;
+
+            // the shading normal on the side the ray came from
+            let normal = if ray.dir.dot(hit.surface_normal) < 0.0 { hit.normal } else { -hit.normal };
+            let lobe = rng.gen::<f32>();
+            let dir = if lobe < mat.transparency {
+                specular = true;
+                self.sample_dielectric(&ray, &hit, rng, &mut throughput)
+            } else if mat.shading == Shading::MetallicRoughness {
+                radiance += throughput * self.direct_light(&hit, normal, -ray.dir, rng);
+                match hit.microfacet().sample(normal, -ray.dir, rng) {
+                    Some(sample) => {
+                        specular = sample.mirror;
+                        throughput *= sample.weight;
+                        sample.dir
+                    }
+                    None => break,
+                }
+            } else if lobe < mat.transparency + (1.0 - mat.transparency) * mat.reflect {
+                specular = true;
+                hit.glossy_reflection(ray.dir, rng)
+            } else {
+                specular = false;
+                radiance += throughput * self.direct_light(&hit, normal, -ray.dir, rng);
+                throughput *= hit.color;
+                cosine_hemisphere(normal, rng)
+            };
+
+            if bounce >= MIN_BOUNCES {
+                let survive = throughput.max_element().min(0.95);
+                if rng.gen::<f32>() >= survive {
+                    break;
+                }
+                throughput /= survive;
+            }
+
+            ray = Ray {
+                org: offset_origin(hit.point, hit.surface_normal, dir),
+                dir,
+            };
+        }
+        radiance
+    }
+
+    /// Light reflected at the hit point towards the viewer, coming straight from the lights. The classic shading
+    /// reflects it diffusely.
+    pub(crate) fn direct_light<R: Rng>(&self, hit: &SurfaceHit, normal: Vec3A, to_viewer: Vec3A, rng: &mut R) -> Vec3A {
+        match hit.mat.shading {
+            Shading::Classic => {
+                let light_color = self.gather_light(hit.point, rng, |sample| {
+                    let cos = normal.dot(sample.dir);
+                    if cos <= 0.0 || !self.light_reaches(hit, sample) {
+                        return Vec3A::ZERO;
+                    }
+                    sample.intensity * cos
+                });
+                // lambertian brdf
+                light_color * hit.color / PI
+            }
+            Shading::MetallicRoughness => {
+                let brdf = hit.microfacet();
+                self.gather_light(hit.point, rng, |sample| {
+                    let reflected = brdf.eval(normal, to_viewer, sample.dir);
+                    if reflected == Vec3A::ZERO || !self.light_reaches(hit, sample) {
+                        return Vec3A::ZERO;
+                    }
+                    sample.intensity * reflected
+                })
+            }
+        }
+    }
+
+    /// Picks reflection or refraction at a transparent surface by the fresnel term and returns the new direction.
+    fn sample_dielectric<R: Rng>(&self, ray: &Ray, hit: &SurfaceHit, rng: &mut R, throughput: &mut Vec3A) -> Vec3A {
+        let mat = &hit.mat;
+        // the normal points out of the object, flip it if the ray leaves the object
+        let entering = ray.dir.dot(hit.surface_normal) < 0.0;
+        let (facing_normal, n1, n2) = if entering { (hit.normal, 1.0, mat.ior) } else { (-hit.normal, mat.ior, 1.0) };
+        let fresnel = schlick(-ray.dir.dot(facing_normal), n1, n2);
+        if rng.gen::<f32>() < fresnel {
+            return hit.reflection;
+        }
+        match refract(ray.dir, facing_normal, n1 / n2) {
+            Some(dir) => {
+                *throughput *= hit.color;
+                dir
+            }
+            // total internal reflection, the fresnel term is 1
+            None => hit.reflection,
+        }
+    }
+}
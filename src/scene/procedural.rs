@@ -0,0 +1,182 @@
+use std::f32::consts::PI;
+use glam::{Vec2, Vec3A};
+use serde::Deserialize;
+
+/// Shape of a procedural texture, every pattern gives values in [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    /// cubes of alternating values, squares on flat surfaces
+    Checker,
+    /// fractal Brownian motion, Perlin noise summed over octaves of rising frequency
+    Noise,
+    /// like `Noise` but summing the absolute values, gives billowy clouds with sharp creases
+    Turbulence,
+    /// veins along the x axis distorted by turbulence
+    Marble,
+    /// rings around the y axis, slightly wobbly
+    Wood,
+    /// distance to the closest of randomly scattered points, cells like scales or cobblestones
+    Voronoi,
+}
+
+/// Where a procedural texture is evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureSpace {
+    /// at the position relative to the object, like carved out of a solid block, so nothing stretches at the poles
+    /// of a sphere and the pattern moves with the object
+    Object,
+    /// on the texture coordinates of the object like an image
+    Uv,
+}
+
+/// Texture computed from the position instead of read from an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Procedural {
+    pub pattern: Pattern,
+    pub space: TextureSpace,
+    /// cells of the pattern per unit, per texture in `Uv` space
+    pub scale: f32,
+    /// number of noise layers, each one has twice the frequency and half the strength of the last
+    pub octaves: u32,
+    /// linear colors for the values 0 and 1
+    pub colors: [Vec3A; 2],
+}
+
+impl Procedural {
+    /// Black and white pattern with 4 octaves in object space.
+    pub fn create(pattern: Pattern, scale: f32) -> Procedural {
+        Procedural {
+            pattern,
+            space: TextureSpace::Object,
+            scale,
+            octaves: 4,
+            colors: [Vec3A::ZERO, Vec3A::ONE],
+        }
+    }
+
+    /// Color at texture coordinate `uv` or position `object_coord` relative to the object, depending on the space.
+    pub fn color(&self, uv: Vec2, object_coord: Vec3A) -> Vec3A {
+        let p = match self.space {
+            TextureSpace::Object => object_coord,
+            TextureSpace::Uv => Vec3A::new(uv.x, uv.y, 0.0),
+        };
+        self.colors[0].lerp(self.colors[1], self.value(p * self.scale))
+    }
+
+    /// value of the pattern in [0, 1]
+    pub fn value(&self, p: Vec3A) -> f32 {
+        let octaves = self.octaves.max(1);
+        let value = match self.pattern {
+            Pattern::Checker => {
+                // flat faces lie on a cell boundary, the bias keeps their hits from flipping between two cells
+                let cell = (p + Vec3A::splat(1e-3)).floor();
+                ((cell.x + cell.y + cell.z) as i64).rem_euclid(2) as f32
+            }
+            Pattern::Noise => fbm(p, octaves) * 0.5 + 0.5,
+            Pattern::Turbulence => turbulence(p, octaves),
+            Pattern::Marble => ((p.x + 4.0 * turbulence(p, octaves)) * PI).sin() * 0.5 + 0.5,
+            Pattern::Wood => {
+                let distance = Vec2::new(p.x, p.z).length() + 0.3 * fbm(p * Vec3A::new(1.0, 0.25, 1.0), octaves);
+                let ring = distance - distance.floor();
+                // light early wood fading into a thin dark ring
+                ring * ring
+            }
+            Pattern::Voronoi => voronoi(p),
+        };
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// Perlin's improved gradient noise, about in [-1, 1] and 0 at the integer lattice points.
+pub fn perlin(p: Vec3A) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    // smoother step, continuous up to the second derivative
+    let fade = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let gradient = gradient(hash(x + dx, y + dy, z + dz));
+        gradient.dot(f - Vec3A::new(dx as f32, dy as f32, dz as f32))
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fade.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fade.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fade.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fade.x);
+    lerp(lerp(x00, x10, fade.y), lerp(x01, x11, fade.y), fade.z)
+}
+
+/// Perlin noise summed over `octaves`, about in [-1, 1]
+pub fn fbm(p: Vec3A, octaves: u32) -> f32 {
+    octave_sum(p, octaves, perlin)
+}
+
+/// absolute Perlin noise summed over `octaves`, in [0, 1]
+pub fn turbulence(p: Vec3A, octaves: u32) -> f32 {
+    octave_sum(p, octaves, |p| perlin(p).abs())
+}
+
+/// sum of `noise` at doubling frequencies and halving amplitudes, divided by the sum of the amplitudes
+fn octave_sum<F: Fn(Vec3A) -> f32>(p: Vec3A, octaves: u32, noise: F) -> f32 {
+    let (mut sum, mut amplitude, mut total, mut p) = (0.0, 1.0, 0.0, p);
+    for _ in 0..octaves {
+        sum += noise(p) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        // the offset keeps the lattice points of the octaves apart
+        p = p * 2.0 + Vec3A::splat(17.31);
+    }
+    if total > 0.0 { sum / total } else { 0.0 }
+}
+
+/// distance to the closest feature point, one random point per lattice cell, clamped to [0, 1]
+pub fn voronoi(p: Vec3A) -> f32 {
+    let cell = p.floor();
+    let mut closest = f32::MAX;
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbour = cell + Vec3A::new(dx as f32, dy as f32, dz as f32);
+                let h = hash(neighbour.x as i32, neighbour.y as i32, neighbour.z as i32);
+                let point = neighbour + Vec3A::new(unit(h), unit(h.rotate_left(11)), unit(h.rotate_left(22)));
+                closest = closest.min(point.distance_squared(p));
+            }
+        }
+    }
+    closest.sqrt().min(1.0)
+}
+
+/// well mixed bits for a lattice point
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841) ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
+
+/// lowest 10 bits as a number in [0, 1)
+fn unit(h: u32) -> f32 {
+    (h & 1023) as f32 / 1024.0
+}
+
+/// one of the 12 directions to the edges of a cube, as in Perlin's improved noise
+fn gradient(h: u32) -> Vec3A {
+    match h % 12 {
+        0 => Vec3A::new(1.0, 1.0, 0.0),
+        1 => Vec3A::new(-1.0, 1.0, 0.0),
+        2 => Vec3A::new(1.0, -1.0, 0.0),
+        3 => Vec3A::new(-1.0, -1.0, 0.0),
+        4 => Vec3A::new(1.0, 0.0, 1.0),
+        5 => Vec3A::new(-1.0, 0.0, 1.0),
+        6 => Vec3A::new(1.0, 0.0, -1.0),
+        7 => Vec3A::new(-1.0, 0.0, -1.0),
+        8 => Vec3A::new(0.0, 1.0, 1.0),
+        9 => Vec3A::new(0.0, -1.0, 1.0),
+        10 => Vec3A::new(0.0, 1.0, -1.0),
+        _ => Vec3A::new(0.0, -1.0, -1.0),
+    }
+}
@@ -0,0 +1,62 @@
+use std::f32::consts::PI;
+use glam::{Vec2, Vec3A};
+use rand::Rng;
+
+/// Two unit vectors that form a right handed orthonormal basis with the unit vector `n`.
+pub fn orthonormal_basis(n: Vec3A) -> (Vec3A, Vec3A) {
+    // Duff et al., "Building an Orthonormal Basis, Revisited"
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let tangent = Vec3A::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bitangent = Vec3A::new(b, sign + n.y * n.y * a, -n.y);
+    (tangent, bitangent)
+}
+
+/// Random direction in the hemisphere around `normal` with a density proportional to the cosine to the normal.
+pub fn cosine_hemisphere<R: Rng>(normal: Vec3A, rng: &mut R) -> Vec3A {
+    let r = rng.gen::<f32>().sqrt();
+    let phi = 2.0 * PI * rng.gen::<f32>();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z).normalize()
+}
+
+/// Sample `index` of `count` jittered samples in the unit square. The square is split into a grid that is as square
+/// as possible and every sample gets a random position in its own cell.
+pub fn stratified_sample<R: Rng>(index: u32, count: u32, rng: &mut R) -> Vec2 {
+    let columns = (count as f32).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    Vec2::new(
+        ((index % columns) as f32 + rng.gen::<f32>()) / columns as f32,
+        ((index / columns) as f32 + rng.gen::<f32>()) / rows as f32,
+    )
+}
+
+/// Maps a point of the unit square uniformly onto the unit disk, keeping strata intact (Shirley and Chiu).
+pub fn concentric_disk(u: Vec2) -> Vec2 {
+    let p = u * 2.0 - Vec2::ONE;
+    if p.x == 0.0 && p.y == 0.0 {
+        return Vec2::ZERO;
+    }
+    let (r, theta) = if p.x.abs() > p.y.abs() {
+        (p.x, PI / 4.0 * (p.y / p.x))
+    } else {
+        (p.y, PI / 2.0 - PI / 4.0 * (p.x / p.y))
+    };
+    Vec2::new(theta.cos(), theta.sin()) * r
+}
+
+/// Maps a point of the unit square uniformly onto a regular polygon with `corners` corners on the unit circle.
+pub fn regular_polygon(corners: u32, u: Vec2) -> Vec2 {
+    let corners = corners.max(3);
+    // pick one of the triangles between the center and two neighbouring corners, reuse the rest of u.x inside it
+    let scaled = u.x * corners as f32;
+    let triangle = (scaled as u32).min(corners - 1);
+    let a = scaled - triangle as f32;
+    let angle = 2.0 * PI / corners as f32;
+    let corner = |i: u32| Vec2::new((angle * i as f32).cos(), (angle * i as f32).sin());
+    // uniform point in the triangle (center, corner i, corner i + 1)
+    let s = u.y.sqrt();
+    corner(triangle) * (s * (1.0 - a)) + corner(triangle + 1) * (s * a)
+}
@@ -1,55 +1,211 @@
 use std::f32::consts::PI;
-use std::rc::Rc;
-use glam::Vec3A;
-use crate::geometry::ray::Ray;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use glam::{Quat, Vec2, Vec3A};
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use rayon::prelude::*;
+use serde::Deserialize;
+use crate::color::ToneMapper;
+use crate::frame_buffer::{create_frame_buffer, FrameBuffer};
+use crate::geometry::bvh::Bvh;
+use crate::geometry::ray::{Ray, RayCone};
 use crate::geometry::sphere::Sphere;
 use crate::geometry::traceable::Traceable;
-use crate::scene::camera::Camera;
-use crate::scene::light::Light;
-use crate::scene::material::Material;
-use crate::scene::texture::{get_pixel, load_texture};
+use crate::geometry::triangle::Triangle;
+use crate::scene::area_light::AreaLight;
+use crate::scene::brdf::{glossy_reflection, Microfacet, MIRROR_ROUGHNESS};
+use crate::scene::camera::{Bokeh, Camera, Projection};
+use crate::scene::cube_map::CubeMap;
+use crate::scene::environment::EnvironmentMap;
+use crate::scene::filter::Filter;
+use crate::scene::light::{LightSample, LightSource, PointLight};
+use crate::scene::material::{Material, NormalMapFormat, Shading};
+use crate::scene::mip_map::Footprint;
+use crate::scene::optics::{refract, schlick};
+use crate::scene::sampling::{orthonormal_basis, stratified_sample};
+use crate::scene::texture::Texture;
+use crate::scene::texture_cache::{TextureCache, TextureLoadError};
+
+/// minimal distance of a hit from the ray origin, avoids self intersections
+const EPSILON: f32 = 0.00001;
+/// distance secondary rays start away from the surface they leave
+const SURFACE_BIAS: f32 = 0.0001;
+
+/// Moves the origin of a secondary ray off the surface, to the side the ray leaves to.
+pub(crate) fn offset_origin(point: Vec3A, normal: Vec3A, dir: Vec3A) -> Vec3A {
+    if dir.dot(normal) >= 0.0 {
+        point + normal * SURFACE_BIAS
+    } else {
+        point - normal * SURFACE_BIAS
+    }
+}
+
+/// step along the surface for measuring the slope of a bump map
+const BUMP_STEP: f32 = 0.001;
+
+/// Normal tilted by the slope of the heights in `bump_map` at `point`, the brightness of the map times `depth`. The
+/// slope is measured with small steps along the surface, so any texture works without tangents.
+fn bump_normal(bump_map: &Texture, depth: f32, point: Vec3A, normal: Vec3A, footprint: &Footprint, collision_obj: &dyn Traceable) -> Vec3A {
+    let height = |p: Vec3A| {
+        let pixel = bump_map.get_pixel(&collision_obj.get_texture_coord(&p), &collision_obj.get_object_coord(&p), footprint);
+        (pixel.x + pixel.y + pixel.z) / 3.0 * depth
+    };
+    let center = height(point);
+    let (a, b) = orthonormal_basis(normal);
+    let slope = |axis: Vec3A| (height(point + axis * BUMP_STEP) - center) / BUMP_STEP;
+    (normal - a * slope(a) - b * slope(b)).normalize()
+}
+
+/// Algorithm used to compute the color of a primary ray.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    /// recursive reflections and refractions with direct light, noise free
+    Whitted,
+    /// Monte Carlo path tracing, converges when several passes are accumulated
+    #[serde(alias = "path")]
+    PathTracer,
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whitted" => Ok(Integrator::Whitted),
+            "path" | "path_tracer" => Ok(Integrator::PathTracer),
+            _ => Err(format!("unknown integrator '{}', expected 'whitted' or 'path'", s)),
+        }
+    }
+}
+
+/// What rays see that don't hit any object.
+enum Sky {
+    /// 8 bit texture on a sphere around the scene, it doesn't give off light
+    Texture(Sphere),
+    /// 8 bit images on the faces of a cube, it doesn't give off light either
+    CubeMap(CubeMap),
+    /// HDR image that also lights the scene
+    Environment(EnvironmentMap),
+}
+
+/// Shading information where a ray hits an object.
+pub(crate) struct SurfaceHit {
+    pub point: Vec3A,
+    /// geometric normal, points out of the object
+    pub surface_normal: Vec3A,
+    /// normal after applying the normal map
+    pub normal: Vec3A,
+    /// mirror direction of the ray
+    pub reflection: Vec3A,
+    /// color of the material or its texture
+    pub color: Vec3A,
+    /// light given off by the material
+    pub emitted: Vec3A,
+    /// metallic and roughness of the material times their maps
+    pub metallic: f32,
+    pub roughness: f32,
+    /// 1 without an occlusion map
+    pub occlusion: f32,
+    pub mat: Arc<Material>,
+}
+
+impl SurfaceHit {
+    /// brdf of the metallic-roughness model at the hit point
+    pub fn microfacet(&self) -> Microfacet {
+        Microfacet {
+            base_color: self.color,
+            metallic: self.metallic,
+            roughness: self.roughness,
+        }
+    }
+
+    /// Reflection of a ray going along `dir`, rough surfaces spread it around the mirror direction `reflection`.
+    pub fn glossy_reflection<R: Rng>(&self, dir: Vec3A, rng: &mut R) -> Vec3A {
+        if self.roughness < MIRROR_ROUGHNESS {
+            return self.reflection;
+        }
+        let normal = if dir.dot(self.normal) < 0.0 { self.normal } else { -self.normal };
+        let mut reflection = glossy_reflection(dir, normal, self.roughness, rng);
+        // don't reflect into the object, mirror it back to the side the ray came from
+        let outside = if dir.dot(self.surface_normal) < 0.0 { self.surface_normal } else { -self.surface_normal };
+        let below = reflection.dot(outside);
+        if below < 0.0 {
+            reflection -= 2.0 * below * outside;
+        }
+        reflection
+    }
+}
 
 pub struct Scene {
     pub camera: Camera,
     objects: Vec<Box<dyn Traceable>>,
-    lights: Vec<Light>,
+    /// acceleration structure over `objects`, built before the first ray after objects were added
+    bvh: OnceLock<Bvh>,
+    lights: Vec<Box<dyn LightSource>>,
+    area_lights: Vec<AreaLight>,
     pub width: i32,
     pub height: i32,
-    sky: Option<Sphere>,
+    /// maximum number of bounces per primary ray
+    pub max_depth: i32,
+    pub integrator: Integrator,
+    /// exposure and tone mapping of `render`
+    pub tone_mapper: ToneMapper,
+    /// primary rays per pixel and pass
+    pub samples_per_pixel: u32,
+    /// how the samples of a pixel are combined
+    pub filter: Filter,
+    /// reflection rays the Whitted shader averages where a camera ray hits a rough surface, deeper reflections
+    /// take one
+    pub glossy_samples: u32,
+    /// how many times longer than wide a texture footprint can be before it gets blurred across, 1 for plain
+    /// trilinear filtering
+    pub max_anisotropy: u32,
+    sky: Option<Sky>,
+    /// the images of the materials, every file is loaded once
+    pub textures: TextureCache,
 }
 
-pub fn create_test_scene(scene: &mut Scene) {
+/// Fills `scene` with the demo scene, the textures are loaded from the `assets` directory.
+pub fn create_test_scene(scene: &mut Scene) -> Result<(), TextureLoadError> {
     let mat_mirror = Material::create(Vec3A::new(0.3, 0.3, 0.3), 0.95);
     // let mat_blue = Material::create(Vec3A::new(0.3, 0.3, 1.0), 0.6);
     let mat_green = Material::create(Vec3A::new(0.2, 1.0, 0.1), 0.3);
     // let mat_mirror = Material::create(Vec3A::new(0.2, 1.0, 0.1), 1.0);
 
-    let mat_bricks = Rc::new(Material {
+    let mat_bricks = Arc::new(Material {
         color: Vec3A::new(1.0, 1.0, 1.0),
         reflect: 0.2,
-        texture: Some(Box::new(load_texture("assets/stone_wall/baseColor.png", 1024))),
-        normal_map: Some(Box::new(load_texture("assets/stone_wall/normal.png", 1024))),
+        texture: Some(Box::new(scene.textures.texture("assets/stone_wall/baseColor.png", true)?)),
+        normal_map: Some(Box::new(scene.textures.texture("assets/stone_wall/normal.png", false)?)),
+        ..Default::default()
     });
 
-    // let magic_material = Rc::new(Material {
-    //     color: Vec3A::new(1.0, 1.0, 1.0),
-    //     reflect: 0.05,
-    //     texture: Some(Box::new(load_texture("assets/magic_stone/emissive.png"))),
-    //     normal_map: Some(Box::new(load_texture("assets/magic_stone/normal.png"))),
-    // });
+    // dark stone with glowing runes
+    let magic_material = Arc::new(Material {
+        color: Vec3A::new(0.1, 0.1, 0.1),
+        reflect: 0.05,
+        emissive: Vec3A::new(1.0, 1.0, 1.0),
+        emissive_intensity: 2.0,
+        emissive_map: Some(Box::new(scene.textures.texture("assets/magic_stone/emissive.png", true)?)),
+        ..Default::default()
+    });
 
-    let magic_reflector = Rc::new(Material {
+    let magic_reflector = Arc::new(Material {
         color: Vec3A::new(0.1, 0.1, 0.1),
         reflect: 0.7,
         texture: None,
-        normal_map: Some(Box::new(load_texture("assets/stone_wall/normal.png", 1024))),
+        normal_map: Some(Box::new(scene.textures.texture("assets/stone_wall/normal.png", false)?)),
+        ..Default::default()
     });
 
-    let stone_castle = Rc::new(Material {
+    let stone_castle = Arc::new(Material {
         color: Vec3A::new(1.0, 1.0, 1.0),
         reflect: 0.05,
-        texture: Some(Box::new(load_texture("assets/stone_castle/baseColor.png", 1024))),
-        normal_map: Some(Box::new(load_texture("assets/stone_castle/normal.png", 1024))),
+        texture: Some(Box::new(scene.textures.texture("assets/stone_castle/baseColor.png", true)?)),
+        normal_map: Some(Box::new(scene.textures.texture("assets/stone_castle/normal.png", false)?)),
+        ..Default::default()
     });
 
     scene.add_sphere(Sphere::create(Vec3A::new(6.0, 0.0, 16.0), 3.0, mat_bricks));
@@ -61,187 +217,490 @@ pub fn create_test_scene(scene: &mut Scene) {
     scene.add_sphere(Sphere::create(Vec3A::new(0.0, 0.0, 22.0), 3.0, mat_mirror.clone()));
     scene.add_sphere(Sphere::create(Vec3A::new(0.0, -6.0, 16.0), 3.0, mat_green.clone()));
     scene.add_sphere(Sphere::create(Vec3A::new(-3.0, 6.0, 12.0), 4.0, magic_reflector.clone()));
+    scene.add_sphere(Sphere::create(Vec3A::new(7.0, 6.0, 20.0), 2.5, magic_material));
 
-    scene.add_light(Light {
-        dir: Vec3A::new(0., 1., 1.).normalize(),
-        direction_sensitivity: 0.0,
-        color: Vec3A::new(1.0, 1.0, 1.0) * 2.0,
+    // falls off with the square of the distance, about 2 at the spheres
+    scene.add_light(PointLight {
+        color: Vec3A::new(1.0, 1.0, 1.0) * 1500.0,
         org: Vec3A::new(0.0, 10.0, -10.0),
     });
-    // scene.add_light(Light {
-    //     dir: Vec3A::new(0., 1., 1.).normalize(),
-    //     direction_sensitivity: 0.0,
-    //     color: Vec3A::new(1.0, 1.0, 1.0) * 1.5,
+    // scene.add_light(PointLight {
+    //     color: Vec3A::new(1.0, 1.0, 1.0) * 400.0,
     //     org: Vec3A::new(0.0, 10.0, 0.0),
     // });
-    // scene.add_light(Light {
+    // scene.add_light(SpotLight {
     //     dir: Vec3A::new(0., -1., 0.).normalize(),
-    //     direction_sensitivity: 0.3,
-    //     color: Vec3A::new(1.0, 0.0, 0.0) * 1.0,
+    //     inner_angle: 0.3,
+    //     outer_angle: 0.6,
+    //     color: Vec3A::new(1.0, 0.0, 0.0) * 800.0,
     //     org: Vec3A::new(20.0, 20.0, 10.0),
     // });
-    // scene.add_light(Light {
+    // scene.add_light(DirectionalLight {
     //     dir: Vec3A::new(0., 1., 1.).normalize(),
-    //     direction_sensitivity: 0.3,
-    //     color: Vec3A::new(0.2, 0.2, 1.0) * 5.0,
-    //     org: Vec3A::new(0.0, -20.0, 10.0),
+    //     color: Vec3A::new(0.2, 0.2, 1.0) * 2.0,
     // });
+    Ok(())
 }
 
 impl Scene {
-    fn find_collision(&self, ray: &Ray) -> Option<(f32, &Box<dyn Traceable>)> {
-        let epsilon = 0.00001;
-        let mut t_min = f32::MAX;
-        let mut obj: Option<&Box<dyn Traceable>> = None;
-        for collision_obj in self.objects.iter() {
-            let mut t: f32 = 0.0;
-            if collision_obj.intersect(&ray, &mut t) {
-                if t < t_min && t > epsilon {
-                    t_min = t;
-                    obj = Some(collision_obj);
+    pub(crate) fn find_collision(&self, ray: &Ray) -> Option<(f32, &dyn Traceable)> {
+        self.bvh()
+            .intersect(&self.objects, ray, EPSILON, f32::MAX)
+            .map(|(t, i)| (t, self.objects[i].as_ref()))
+    }
+
+    /// true if an object blocks the ray before it travelled `max_dist`
+    pub(crate) fn is_occluded(&self, ray: &Ray, max_dist: f32) -> bool {
+        self.bvh().occluded(&self.objects, ray, EPSILON, max_dist)
+    }
+
+    /// Builds the bvh over all objects once, adding objects one by one doesn't rebuild it every time.
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| Bvh::build(&self.objects))
+    }
+
+    /// Checks that no object blocks the shadow ray from the hit point towards a light sample.
+    pub(crate) fn light_reaches(&self, hit: &SurfaceHit, sample: &LightSample) -> bool {
+        let ray_to_light = Ray {
+            org: offset_origin(hit.point, hit.surface_normal, sample.dir),
+            dir: sample.dir,
+        };
+        !self.is_occluded(&ray_to_light, sample.dist)
+    }
+
+    /// Looks up the material, texture and normal map at the point where `ray` hits `collision_obj`. The textures are
+    /// filtered over the cross section of `cone`.
+    pub(crate) fn surface_hit(&self, ray: &Ray, cone: RayCone, t: f32, collision_obj: &dyn Traceable) -> SurfaceHit {
+        let collision = ray.point_at(t);
+        // normal of the object at the ray
+        let tex_coord = collision_obj.get_texture_coord(&collision);
+        let object_coord = collision_obj.get_object_coord(&collision);
+
+        let (mut normal, mut reflection) = collision_obj.intersection_normal(ray, collision);
+        let surface_normal = normal;
+        let mat = collision_obj.get_mat();
+        // the footprint costs extra texture coordinates, procedural textures and plain colors don't need it
+        let footprint = if mat.has_image_maps() {
+            self.footprint(ray, cone.width_at(t), collision, surface_normal, tex_coord, collision_obj)
+        } else {
+            Footprint::default()
+        };
+
+        // check normal map, procedural ones hold heights like bump maps
+        if let Some(normal_map) = mat.normal_map.as_ref().filter(|map| !map.is_procedural()) {
+            let normal_pixel = normal_map.get_pixel(&tex_coord, &object_coord, &footprint);
+            let l = (normal_pixel - Vec3A::new(0.5, 0.5, 0.5)).normalize();
+
+            // coord system from the texture coordinates, the bitangent points down the image
+            let (tangent, bitangent) = collision_obj.tangents(&collision, normal);
+            let up = match mat.normal_map_format {
+                NormalMapFormat::OpenGl => -bitangent,
+                NormalMapFormat::DirectX => bitangent,
+            };
+            normal = (l.z * normal + up * l.y + tangent * l.x).normalize();
+        }
+        let bump_maps = [mat.normal_map.as_ref().filter(|map| map.is_procedural()), mat.bump_map.as_ref()];
+        for bump_map in bump_maps.iter().flatten() {
+            normal = bump_normal(bump_map, mat.bump_depth, collision, normal, &footprint, collision_obj);
+        }
+
+        if mat.normal_map.is_some() || mat.bump_map.is_some() {
+            reflection = ray.dir - 2.0 * ray.dir.dot(normal) * normal;
+            // the maps can tilt the reflection into the object, mirror it back to the side the ray came from
+            let outside = if ray.dir.dot(surface_normal) < 0.0 { surface_normal } else { -surface_normal };
+            let below = reflection.dot(outside);
+            if below < 0.0 {
+                reflection -= 2.0 * below * outside;
+            }
+        }
+
+        // texture
+        let color = if let Some(texture) = &mat.texture {
+            texture.get_color(&tex_coord, &object_coord, &footprint)
+        } else {
+            mat.color
+        };
+
+        // the maps use the channels of glTF's metallic-roughness and occlusion textures
+        let channel = |map: &Option<Box<Texture>>, channel: usize| match map {
+            Some(map) => map.get_pixel(&tex_coord, &object_coord, &footprint)[channel],
+            None => 1.0,
+        };
+
+        SurfaceHit {
+            point: collision,
+            surface_normal,
+            normal,
+            reflection,
+            color,
+            emitted: mat.emitted(&tex_coord, &object_coord, &footprint),
+            metallic: mat.metallic * channel(&mat.metallic_map, 2),
+            roughness: mat.roughness * channel(&mat.roughness_map, 1),
+            occlusion: channel(&mat.occlusion_map, 0),
+            mat,
+        }
+    }
+
+    /// Texture coordinates covered by a cone `width` wide where `ray` hits `collision_obj` at `point`. The cross
+    /// section of the cone is stretched along the surface and mapped to the texture.
+    fn footprint(&self, ray: &Ray, width: f32, point: Vec3A, normal: Vec3A, tex_coord: Vec2, collision_obj: &dyn Traceable) -> Footprint {
+        let (a, b) = orthonormal_basis(ray.dir);
+        let cos = ray.dir.dot(normal);
+        // grazing rays stretch the footprint without bound
+        let cos = if cos.abs() < 0.05 { 0.05f32.copysign(cos) } else { cos };
+        let delta = |axis: Vec3A| {
+            // move along the ray onto the tangent plane
+            let offset = axis * width;
+            let offset = offset - ray.dir * (offset.dot(normal) / cos);
+            let d = collision_obj.get_texture_coord(&(point + offset)) - tex_coord;
+            // textures repeat, a step across the seam is a small step
+            d - d.round()
+        };
+        Footprint {
+            dx: delta(a),
+            dy: delta(b),
+            max_anisotropy: self.max_anisotropy,
+        }
+    }
+
+    /// color of the sky in direction `dir`
+    pub(crate) fn sky_color(&self, dir: Vec3A) -> Vec3A {
+        match &self.sky {
+            Some(Sky::Texture(sky)) => {
+                let t = sky.mat.clone();
+                if let Some(texture) = &t.texture {
+                    let tex_coord = sky.get_texture_coord(&dir);
+                    return texture.get_color(&tex_coord, &dir, &Footprint::default());
                 }
+                Vec3A::ZERO
             }
+            Some(Sky::CubeMap(cube_map)) => cube_map.color(dir),
+            Some(Sky::Environment(environment)) => environment.radiance(dir),
+            None => Vec3A::ZERO,
         }
-        if let Some(collision_obj) = obj {
-            return Some((t_min, collision_obj));
+    }
+
+    /// The sky is sampled as a light source. Paths that reach it after a diffuse bounce were already counted by the
+    /// direct light.
+    pub(crate) fn sky_is_light(&self) -> bool {
+        matches!(self.sky, Some(Sky::Environment(_)))
+    }
+
+    /// all light sources, the area lights and the environment map included
+    pub(crate) fn light_sources(&self) -> impl Iterator<Item = &dyn LightSource> {
+        let environment = match &self.sky {
+            Some(Sky::Environment(environment)) => Some(environment as &dyn LightSource),
+            _ => None,
+        };
+        self.lights
+            .iter()
+            .map(|light| light.as_ref())
+            .chain(self.area_lights.iter().map(|light| light as &dyn LightSource))
+            .chain(environment)
+    }
+
+    /// Sums up `shade` over the samples of all light sources at `point`. Lights with several samples are sampled
+    /// stratified and averaged, the fraction of shadow rays that gets through makes the penumbra.
+    pub(crate) fn gather_light<R: Rng, F: Fn(&LightSample) -> Vec3A>(&self, point: Vec3A, rng: &mut R, shade: F) -> Vec3A {
+        let mut light_color = Vec3A::ZERO;
+        for light in self.light_sources() {
+            let samples = light.samples();
+            for i in 0..samples {
+                if let Some(sample) = light.sample(point, stratified_sample(i, samples, rng)) {
+                    light_color += shade(&sample) / samples as f32;
+                }
+            }
         }
-        return None;
+        light_color
     }
 
-    fn shoot_ray(&self, ray: &Ray, iterations: i32) -> Vec3A {
-        if iterations <= 0 {
+    /// Light from a light sample that reaches the hit point, unless an object blocks it.
+    fn shade_light(&self, hit: &SurfaceHit, sample: &LightSample) -> Vec3A {
+        let dir_to_light = sample.dir;
+        if !self.light_reaches(hit, sample) {
+            // blocked -> no light
             return Vec3A::ZERO;
         }
-        if let Some((t, collision_obj)) = self.find_collision(ray) {
-            let collision = ray.point_at(t);
-            // normal of the object at the ray
-            let tex_coord = collision_obj.get_texture_coord(&collision);
+        // goes through to light
+        let specular = 0.1;
+        let specular_reflection = f32::max(specular - dir_to_light.angle_between(hit.reflection) / PI, 0.0) / specular;
 
-            let (mut normal, mut reflection) = collision_obj.intersection_normal(&ray, collision);
-            let mat = collision_obj.get_mat();
+        let diffusion_angle = hit.normal.angle_between(dir_to_light) / PI;
+        let diffusion = 0.5;
+        let diffusion_comp = f32::max(diffusion - diffusion_angle, 0.0) / diffusion;
 
-            // check normal map
-            if let Some(normal_map) = &mat.normal_map {
-                let normal_pixel = get_pixel(normal_map, &tex_coord);
-                let l = (normal_pixel - Vec3A::new(0.5, 0.5, 0.5)).normalize();
+        let clr = sample.intensity * (diffusion_comp + specular_reflection * 0.1);
+        clr * hit.color
+    }
 
-                // coord system from the normal
-                let to_right = normal.cross(-Vec3A::Y);
-                let to_up = normal.cross(-to_right);
+    /// Radiance of the closest area light the ray hits before `t_max`.
+    pub(crate) fn hit_area_light(&self, ray: &Ray, t_max: f32) -> Option<Vec3A> {
+        self.area_lights
+            .iter()
+            .filter_map(|light| light.intersect(ray).map(|t| (t, light)))
+            .filter(|(t, _)| *t < t_max)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, light)| light.color)
+    }
 
-                normal = l.z * normal + to_up * l.y + to_right * l.x;
-                reflection = l.z * reflection + to_up * l.y + to_right * l.x;
-            }
+    fn shoot_ray<R: Rng>(&self, ray: &Ray, cone: RayCone, iterations: i32, rng: &mut R) -> Vec3A {
+        if iterations <= 0 {
+            return Vec3A::ZERO;
+        }
+        let collision = self.find_collision(ray);
+        if let Some(emitted) = self.hit_area_light(ray, collision.map_or(f32::MAX, |(t, _)| t)) {
+            return emitted;
+        }
+        if let Some((t, collision_obj)) = collision {
+            let hit = self.surface_hit(ray, cone, t, collision_obj);
+            let SurfaceHit { point: collision, surface_normal, normal, color, emitted, ref mat, .. } = hit;
 
             let angle = normal.angle_between(-ray.dir) / PI;
             let angle_comp = f32::max(0.5 - angle, 0.0) / 0.5;
-            // use the angle to calc the ambient light
+            let facing_normal = if ray.dir.dot(surface_normal) < 0.0 { normal } else { -normal };
 
-            // texture
-            let color = if let Some(texture) = &mat.texture {
-                get_pixel(texture, &tex_coord)
-            } else {
-                mat.color
+            // shoot towards lights
+            let light_color = match mat.shading {
+                Shading::Classic => self.gather_light(collision, rng, |sample| self.shade_light(&hit, sample)),
+                Shading::MetallicRoughness => self.direct_light(&hit, facing_normal, -ray.dir, rng),
             };
-            let ambient_color = color * angle_comp;
 
-            let mut light_color = Vec3A::ZERO;
-            // shoot towards lights
-            for light in self.lights.iter() {
-                let dir_to_light = (light.org - collision).normalize();
-                let ray_to_light = Ray {
-                    org: collision,
-                    dir: dir_to_light,
+            // reflect ray, rough surfaces average several rays around the mirror direction where the camera rays hit
+            let reflection_samples = if hit.roughness >= MIRROR_ROUGHNESS && iterations == self.max_depth {
+                self.glossy_samples.max(1)
+            } else {
+                1
+            };
+            let mut shot = Vec3A::ZERO;
+            for _ in 0..reflection_samples {
+                let reflection = hit.glossy_reflection(ray.dir, rng);
+                let refection_ray = Ray {
+                    org: offset_origin(collision, surface_normal, reflection),
+                    dir: reflection,
                 };
-                if let Some(_) = self.find_collision(&ray_to_light) {
-                    // blocked -> no light
-                } else {
-                    // goes through to light
-                    let angle_light_dir_ray = light.dir.angle_between(-dir_to_light) / PI;
-                    let dir_angle_comp = light.direction_sensitivity * f32::max(0.5 - angle_light_dir_ray, 0.0) * 2.0;
-
-                    let specular = 0.1;
-                    let specular_reflection = f32::max(specular - dir_to_light.angle_between(reflection) / PI, 0.0) / specular;
-
-                    let diffusion_angle = normal.angle_between(dir_to_light) / PI;
-                    let diffusion = 0.5;
-                    let diffusion_comp = f32::max(diffusion - diffusion_angle, 0.0) / diffusion;
-
-                    let ambient_component = 1.0 - light.direction_sensitivity;
-                    let clr = light.color * ((dir_angle_comp + ambient_component) * diffusion_comp + specular_reflection * 0.1);
-
-                    light_color.x += clr.x * color.x;
-                    light_color.y += clr.y * color.y;
-                    light_color.z += clr.z * color.z;
+                shot += self.shoot_ray(&refection_ray, cone.advance(t), iterations - 1, rng);
+            }
+            shot /= reflection_samples as f32;
+
+            let surface_color = match mat.shading {
+                Shading::Classic => {
+                    // use the angle to calc the ambient light
+                    let ambient_color = color * angle_comp;
+                    let non_reflect = 1.0 - mat.reflect;
+
+                    0.3 * ambient_color * non_reflect + light_color * 0.55 * non_reflect + 1.0 * shot * mat.reflect
+                }
+                Shading::MetallicRoughness => {
+                    let brdf = hit.microfacet();
+                    // the same ambient light as above, only for the base layer
+                    let ambient_color = brdf.diffuse_color() * angle_comp * hit.occlusion;
+                    let reflectance = brdf.reflectance(facing_normal.dot(-ray.dir)) * hit.occlusion;
+
+                    0.3 * ambient_color + light_color + shot * reflectance
                 }
+            };
+            if mat.transparency <= 0.0 {
+                return surface_color + emitted;
             }
 
-            // reflect ray
-            let refection_ray = Ray {
-                org: collision,
-                dir: reflection,
+            // the normal points out of the object, flip it if the ray leaves the object
+            let entering = ray.dir.dot(surface_normal) < 0.0;
+            let (facing_normal, n1, n2) = if entering { (normal, 1.0, mat.ior) } else { (-normal, mat.ior, 1.0) };
+            let fresnel = schlick(-ray.dir.dot(facing_normal), n1, n2);
+            let refracted = match refract(ray.dir, facing_normal, n1 / n2) {
+                Some(dir) => {
+                    let refraction_ray = Ray {
+                        org: offset_origin(collision, surface_normal, dir),
+                        dir,
+                    };
+                    self.shoot_ray(&refraction_ray, cone.advance(t), iterations - 1, rng) * color
+                }
+                // total internal reflection, the fresnel term is 1
+                None => Vec3A::ZERO,
             };
-            let shot = self.shoot_ray(&refection_ray, iterations - 1);
+            let transmitted = fresnel * shot + (1.0 - fresnel) * refracted;
 
-            let non_reflect = 1.0 - mat.reflect;
+            return surface_color * (1.0 - mat.transparency) + transmitted * mat.transparency + emitted;
+        }
+        self.sky_color(ray.dir)
+    }
+
+    /// Renders the scene into `screen` (sRGB RGBA, `width * height * 4` bytes). The rows are rendered in parallel.
+    pub fn render(&self, screen: &mut [u8]) {
+        let mut frame = create_frame_buffer(self.width, self.height);
+        self.render_pass(&mut frame);
+        frame.write_rgba(screen, &self.tone_mapper);
+    }
 
-            return 0.3 * ambient_color * non_reflect + light_color * 0.55 * non_reflect + 1.0 * shot * mat.reflect;
+    /// Adds one pass to `frame`, which is resized if it doesn't match the scene. A pass takes `samples_per_pixel`
+    /// jittered samples per pixel and combines them with the `filter`. Each pass uses different random numbers, so
+    /// the average of many passes converges.
+    pub fn render_pass(&self, frame: &mut FrameBuffer) {
+        if frame.width != self.width || frame.height != self.height {
+            frame.resize(self.width, self.height);
         }
-        if let Some(sky) = &self.sky {
-            let t = sky.mat.clone();
-            if let Some(texture) = &t.texture {
-                let tex_coord = sky.get_texture_coord(&ray.dir);
-                return get_pixel(&texture, &tex_coord);
+        let pass = frame.samples;
+        let samples = self.samples_per_pixel.max(1);
+        // a single sample of the first pass goes through the pixel center, like a plain ray tracer
+        let jitter = samples > 1 || pass > 0;
+        let radius = self.filter.radius();
+        frame.par_rows_mut().enumerate().for_each(|(row, pixels)| {
+            for (x, pixel) in pixels.iter_mut().enumerate() {
+                let index = row as u64 * self.width as u64 + x as u64;
+                let mut rng = SmallRng::seed_from_u64((pass as u64) << 32 | index);
+                if !jitter {
+                    let lens = self.lens_sample(&mut rng);
+                    let ray = self.camera.primary_ray(self.width, self.height, x as f32, row as f32, lens);
+                    *pixel += self.trace(&ray, &mut rng);
+                    continue;
+                }
+
+                let mut color = Vec3A::ZERO;
+                let mut weight_sum = 0.0;
+                for i in 0..samples {
+                    let offset = (stratified_sample(i, samples, &mut rng) * 2.0 - Vec2::ONE) * radius;
+                    let weight = self.filter.weight(offset.x, offset.y);
+                    let lens = self.lens_sample(&mut rng);
+                    let ray = self.camera.primary_ray(self.width, self.height, x as f32 + offset.x, row as f32 + offset.y, lens);
+                    color += self.trace(&ray, &mut rng) * weight;
+                    weight_sum += weight;
+                }
+                // the negative lobes of the mitchell filter can cancel out a few samples
+                *pixel += if weight_sum.abs() > 1e-6 { color / weight_sum } else { color / samples as f32 };
             }
+        });
+        frame.samples += 1;
+    }
+
+    fn lens_sample<R: Rng>(&self, rng: &mut R) -> Vec2 {
+        if self.camera.aperture <= 0.0 {
+            return Vec2::ZERO;
         }
-        Vec3A::ZERO
+        self.camera.bokeh.sample(Vec2::new(rng.gen(), rng.gen()))
     }
 
-    pub fn render(&self, screen: &mut [u8]) {
-        let mut chunks = screen.chunks_mut(4);
-        self.camera.render(self.width, self.height, |ray| {
-            let pixel = chunks.next().unwrap();
+    /// Point of the closest object under the pixel coordinates (`x`, `y`), e.g. for focusing on a click.
+    pub fn pick(&self, x: f32, y: f32) -> Option<Vec3A> {
+        let ray = self.camera.primary_ray(self.width, self.height, x, y, Vec2::ZERO);
+        self.find_collision(&ray).map(|(t, _)| ray.point_at(t))
+    }
 
-            let color_vec = self.shoot_ray(ray, 4);
-            let color = [(color_vec.x * 255.0) as u8, (color_vec.y * 255.0) as u8, (color_vec.z * 255.0) as u8, 0xff];
-            pixel.copy_from_slice(&color)
-        })
+    /// color of a primary ray with the selected integrator
+    pub(crate) fn trace<R: Rng>(&self, ray: &Ray, rng: &mut R) -> Vec3A {
+        let cone = self.camera.pixel_cone(self.width, self.height);
+        match self.integrator {
+            Integrator::Whitted => self.shoot_ray(ray, cone, self.max_depth, rng),
+            Integrator::PathTracer => self.trace_path(ray, cone, rng),
+        }
     }
 
-    pub fn create(width: i32, height: i32) -> Self {
-        let sky = Sphere::create(Vec3A::ZERO, 1.0, Rc::new(Material {
-            color: Vec3A::ZERO,
-            reflect: 0.0,
-            normal_map: None,
-            texture: Some(Box::new(load_texture("assets/skybox.jpg", 1024))),
-        }));
+    /// Creates a scene with the default camera and the sky from the `assets` directory.
+    pub fn create(width: i32, height: i32) -> Result<Self, TextureLoadError> {
+        let mut scene = Scene::create_empty(width, height);
+        let sky = scene.textures.texture("assets/skybox.jpg", true)?;
+        scene.set_sky(Some(sky));
+        Ok(scene)
+    }
+
+    /// Creates a scene with the default camera but without objects, lights or sky.
+    pub fn create_empty(width: i32, height: i32) -> Self {
         Scene {
             camera: Camera {
                 org: Vec3A::new(0.0, 5.0, -5.0),
-                dir: Vec3A::Z,
+                orientation: Quat::IDENTITY,
                 zoom: 1.5,
                 screen_dist: 4.0,
+                aperture: 0.0,
+                focus_dist: 20.0,
+                bokeh: Bokeh::Disk,
+                projection: Projection::Perspective,
             },
             width,
             height,
+            max_depth: 4,
+            integrator: Integrator::Whitted,
+            tone_mapper: ToneMapper::default(),
+            samples_per_pixel: 1,
+            filter: Filter::Box,
+            glossy_samples: 8,
+            max_anisotropy: 1,
             objects: Vec::new(),
+            bvh: OnceLock::new(),
             lights: Vec::new(),
-            sky: Some(sky),
+            area_lights: Vec::new(),
+            sky: None,
+            textures: TextureCache::create(1024),
         }
     }
 
+    /// Sets the sRGB texture that is shown when a ray doesn't hit any object.
+    pub fn set_sky(&mut self, texture: Option<Texture>) {
+        self.sky = texture.map(|texture| Sky::Texture(Sphere::create(Vec3A::ZERO, 1.0, Arc::new(Material {
+            color: Vec3A::ZERO,
+            texture: Some(Box::new(texture)),
+            ..Default::default()
+        }))));
+    }
+
+    /// number of images loaded for the materials and the sky
+    pub fn texture_count(&self) -> usize {
+        self.textures.len() + matches!(self.sky, Some(Sky::Environment(_))) as usize
+    }
+
+    /// Bytes taken by the images of the materials and the sky, with their mip maps. Skies that weren't loaded through
+    /// `textures` are left out, except for the environment map.
+    pub fn texture_memory(&self) -> usize {
+        let environment = match &self.sky {
+            Some(Sky::Environment(environment)) => environment.memory(),
+            _ => 0,
+        };
+        self.textures.memory() + environment
+    }
+
+    /// Replaces the sky with a cube map.
+    pub fn set_cube_map(&mut self, cube_map: CubeMap) {
+        self.sky = Some(Sky::CubeMap(cube_map));
+    }
+
+    /// Replaces the sky with an HDR environment map, which also lights the scene.
+    pub fn set_environment(&mut self, environment: EnvironmentMap) {
+        self.sky = Some(Sky::Environment(environment));
+    }
+
     pub fn add_sphere(&mut self, sphere: Sphere) {
-        self.objects.push(Box::new(sphere));
+        self.add_object(sphere);
     }
 
-    pub fn add_light(&mut self, light: Light) {
-        self.lights.push(light);
+    /// Adds any traceable, e.g. a `Plane` or an `AxisAlignedBox`.
+    pub fn add_object<T: Traceable + 'static>(&mut self, object: T) {
+        self.objects.push(Box::new(object));
+        self.bvh = OnceLock::new();
+    }
+
+    /// Adds all triangles of a mesh, e.g. from `obj_loader::load_obj`.
+    pub fn add_mesh(&mut self, triangles: Vec<Triangle>) {
+        for triangle in triangles {
+            self.objects.push(Box::new(triangle));
+        }
+        self.bvh = OnceLock::new();
+    }
+
+    /// Adds a `PointLight`, `SpotLight` or `DirectionalLight`.
+    pub fn add_light<T: LightSource + 'static>(&mut self, light: T) {
+        self.lights.push(Box::new(light));
+    }
+
+    pub fn add_area_light(&mut self, light: AreaLight) {
+        self.area_lights.push(light);
     }
 
     pub fn update(&mut self) {
         for x in self.objects.iter_mut() {
             x.update();
         }
+        if let Some(bvh) = self.bvh.get_mut() {
+            bvh.refit(&self.objects);
+        }
     }
 }
 
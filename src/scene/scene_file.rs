@@ -0,0 +1,797 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use glam::{Affine3A, Quat, Vec2, Vec3, Vec3A};
+use image::ImageError;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+use crate::color::ToneMapping;
+use crate::geometry::axis_aligned_box::AxisAlignedBox;
+use crate::geometry::plane::Plane;
+use crate::geometry::sphere::Sphere;
+use crate::scene::area_light::{AreaLight, LightShape};
+use crate::scene::camera::{Bokeh, Camera, Projection};
+use crate::scene::cube_map::CubeMap;
+use crate::scene::environment::EnvironmentMap;
+use crate::scene::filter::Filter;
+use crate::scene::light::{DirectionalLight, PointLight, SpotLight};
+use crate::scene::material::{Material, NormalMapFormat, Shading};
+use crate::scene::obj_loader::{load_obj, ObjLoadError};
+use crate::scene::procedural::{Pattern, Procedural, TextureSpace};
+use crate::scene::scene::{Integrator, Scene};
+use crate::scene::texture::{Texture, TextureFilter, UvTransform, WrapMode};
+use crate::scene::texture_cache::TextureLoadError;
+
+/// Error returned by `Scene::load` and `Scene::from_toml`.
+#[derive(Debug)]
+pub enum SceneLoadError {
+    /// the scene file could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// the scene file is not a valid scene description
+    Parse { path: PathBuf, source: toml::de::Error },
+    /// a texture referenced by the scene could not be loaded
+    Texture { path: PathBuf, source: ImageError },
+    /// a mesh could not be loaded
+    Mesh { source: ObjLoadError },
+    /// an object references a material that is not defined in `[materials]`
+    UnknownMaterial { name: String },
+    /// the settings of the scene file contradict each other or are incomplete
+    Invalid { reason: String },
+}
+
+impl fmt::Display for SceneLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneLoadError::Io { path, source } => write!(f, "could not read scene file {}: {}", path.display(), source),
+            SceneLoadError::Parse { path, source } => write!(f, "invalid scene file {}: {}", path.display(), source),
+            SceneLoadError::Texture { path, source } => write!(f, "could not load texture {}: {}", path.display(), source),
+            SceneLoadError::Mesh { source } => write!(f, "{}", source),
+            SceneLoadError::UnknownMaterial { name } => write!(f, "unknown material '{}'", name),
+            SceneLoadError::Invalid { reason } => write!(f, "invalid scene: {}", reason),
+        }
+    }
+}
+
+impl From<TextureLoadError> for SceneLoadError {
+    fn from(err: TextureLoadError) -> Self {
+        SceneLoadError::Texture {
+            path: err.path,
+            source: err.source,
+        }
+    }
+}
+
+impl Error for SceneLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneLoadError::Io { source, .. } => Some(source),
+            SceneLoadError::Parse { source, .. } => Some(source),
+            SceneLoadError::Texture { source, .. } => Some(source),
+            SceneLoadError::Mesh { source } => Some(source),
+            SceneLoadError::UnknownMaterial { .. } | SceneLoadError::Invalid { .. } => None,
+        }
+    }
+}
+
+/**
+Root of a scene file. Texture paths are relative to the directory of the scene file.
+
+```toml
+sky = "../assets/skybox.jpg"
+
+# instead of `sky`, a cube map from a cross layout or from six faces
+# [cube_map]
+# cross = "../assets/sky_cross.png"
+
+# instead of `sky`, an HDR image that also lights the scene
+# [environment]
+# path = "../assets/studio.hdr"
+# rotation = 90.0
+# intensity = 1.0
+# samples = 16
+
+[camera]
+org = [0.0, 5.0, -5.0]
+dir = [0.0, 0.0, 1.0]
+
+[materials.green]
+color = [0.2, 1.0, 0.1]
+reflect = 0.3
+
+[materials.glass]
+transparency = 0.9
+ior = 1.5
+
+[materials.marble]
+texture = { pattern = "marble", scale = 0.5, colors = [[0.9, 0.9, 0.85], [0.3, 0.3, 0.35]] }
+
+[[spheres]]
+center = [0.0, -6.0, 16.0]
+radius = 3.0
+material = "green"
+
+[[planes]]
+point = [0.0, -3.0, 0.0]
+normal = [0.0, 1.0, 0.0]
+texture_scale = 4.0
+material = "green"
+
+[[boxes]]
+min = [-8.0, -3.0, 18.0]
+max = [-5.0, 0.0, 21.0]
+material = "green"
+
+[[meshes]]
+path = "../assets/teapot.obj"
+position = [0.0, 0.0, 10.0]
+scale = 2.0
+
+[[lights]]
+type = "point"
+org = [0.0, 10.0, -10.0]
+intensity = 1500.0
+
+[[lights]]
+type = "directional"
+dir = [1.0, -2.0, 1.0]
+color = [1.0, 0.9, 0.8]
+```
+ */
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    sky: Option<PathBuf>,
+    /// six images on a cube, instead of `sky`
+    cube_map: Option<CubeMapDescription>,
+    /// HDR sky that lights the scene, instead of `sky` or `cube_map`
+    environment: Option<EnvironmentDescription>,
+    /// textures wider than this are downsized when loading, 0 keeps the original size
+    #[serde(default = "default_texture_size")]
+    texture_size: u32,
+    max_depth: Option<i32>,
+    integrator: Option<Integrator>,
+    tone_mapping: Option<ToneMapping>,
+    /// in stops
+    #[serde(default)]
+    exposure: f32,
+    samples_per_pixel: Option<u32>,
+    filter: Option<Filter>,
+    /// reflection rays of the Whitted shader on rough surfaces
+    glossy_samples: Option<u32>,
+    /// longest texture footprint that is filtered without blurring, relative to its width
+    max_anisotropy: Option<u32>,
+    camera: Option<CameraDescription>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    spheres: Vec<SphereDescription>,
+    #[serde(default)]
+    planes: Vec<PlaneDescription>,
+    #[serde(default)]
+    boxes: Vec<BoxDescription>,
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    lights: Vec<LightDescription>,
+    #[serde(default)]
+    area_lights: Vec<AreaLightDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    org: [f32; 3],
+    #[serde(default = "default_camera_dir")]
+    dir: [f32; 3],
+    /// point to look at, replaces `dir`
+    target: Option<[f32; 3]>,
+    #[serde(default = "default_camera_up")]
+    up: [f32; 3],
+    /// rotation around the view direction in degrees, positive tilts the top of the screen to the right
+    #[serde(default)]
+    roll: f32,
+    #[serde(default = "default_zoom")]
+    zoom: f32,
+    #[serde(default = "default_screen_dist")]
+    screen_dist: f32,
+    /// lens radius, 0 keeps everything in focus
+    #[serde(default)]
+    aperture: f32,
+    #[serde(default = "default_focus_dist")]
+    focus_dist: f32,
+    /// number of aperture blades for polygonal bokeh, a round lens if missing
+    bokeh_blades: Option<u32>,
+    projection: Option<Projection>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    #[serde(default = "default_color")]
+    color: [f32; 3],
+    #[serde(default)]
+    reflect: f32,
+    #[serde(default = "default_ior")]
+    ior: f32,
+    #[serde(default)]
+    transparency: f32,
+    texture: Option<TextureDescription>,
+    normal_map: Option<TextureDescription>,
+    #[serde(default = "default_normal_map_format")]
+    normal_map_format: NormalMapFormat,
+    bump_map: Option<TextureDescription>,
+    /// for bump maps and procedural normal maps
+    #[serde(default = "default_bump_depth")]
+    bump_depth: f32,
+    #[serde(default = "default_shading")]
+    shading: Shading,
+    /// 1 if only `metallic_map` is given, 0 otherwise
+    metallic: Option<f32>,
+    /// 1 if only `roughness_map` is given, 0 otherwise
+    roughness: Option<f32>,
+    metallic_map: Option<TextureDescription>,
+    roughness_map: Option<TextureDescription>,
+    occlusion_map: Option<TextureDescription>,
+    /// white if only `emissive_map` is given, black otherwise
+    emissive: Option<[f32; 3]>,
+    #[serde(default = "default_intensity")]
+    emissive_intensity: f32,
+    /// multiplied with `emissive`
+    emissive_map: Option<TextureDescription>,
+}
+
+/// Path of a texture, a table with the path and how the texture is looked up, or a procedural pattern.
+enum TextureDescription {
+    Path(PathBuf),
+    Settings(TextureSettings),
+    Procedural(ProceduralDescription),
+}
+
+impl<'de> Deserialize<'de> for TextureDescription {
+    /// Tables with a `pattern` are procedural, the others settings. Unlike an untagged enum this keeps the errors of
+    /// the table, like the name of a misspelt key.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = toml::Value::deserialize(deserializer)?;
+        let description = match value {
+            toml::Value::String(path) => return Ok(TextureDescription::Path(PathBuf::from(path))),
+            toml::Value::Table(ref table) if table.contains_key("pattern") => value.try_into().map(TextureDescription::Procedural),
+            toml::Value::Table(_) => value.try_into().map(TextureDescription::Settings),
+            _ => return Err(D::Error::custom("expected the path of a texture or a table with `path` or `pattern`")),
+        };
+        description.map_err(D::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureSettings {
+    path: PathBuf,
+    #[serde(default = "default_wrap")]
+    wrap: WrapMode,
+    #[serde(default = "default_texture_filter")]
+    filter: TextureFilter,
+    #[serde(default = "default_uv_scale")]
+    scale: [f32; 2],
+    /// counter-clockwise in degrees
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    offset: [f32; 2],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProceduralDescription {
+    pattern: Pattern,
+    #[serde(default = "default_texture_space")]
+    space: TextureSpace,
+    #[serde(default = "default_pattern_scale")]
+    scale: f32,
+    #[serde(default = "default_octaves")]
+    octaves: u32,
+    /// linear colors for the values 0 and 1, black and white by default
+    #[serde(default = "default_pattern_colors")]
+    colors: [[f32; 3]; 2],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDescription {
+    center: [f32; 3],
+    radius: f32,
+    material: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneDescription {
+    point: [f32; 3],
+    normal: [f32; 3],
+    /// size of one repetition of the texture
+    #[serde(default = "default_scale")]
+    texture_scale: f32,
+    material: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoxDescription {
+    min: [f32; 3],
+    max: [f32; 3],
+    #[serde(default = "default_scale")]
+    texture_scale: f32,
+    material: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDescription {
+    /// Wavefront OBJ file
+    path: PathBuf,
+    #[serde(default)]
+    position: [f32; 3],
+    #[serde(default = "default_scale")]
+    scale: f32,
+    /// replaces the materials from the MTL library
+    material: Option<String>,
+}
+
+/// `color` is multiplied by `intensity`, point and spot lights fall off with the square of the distance
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightDescription {
+    Point {
+        org: [f32; 3],
+        #[serde(default = "default_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// cone angles in degrees from `dir`
+    Spot {
+        org: [f32; 3],
+        #[serde(default = "default_light_dir")]
+        dir: [f32; 3],
+        #[serde(default)]
+        inner_angle: f32,
+        outer_angle: f32,
+        #[serde(default = "default_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// sun light travelling along `dir`
+    Directional {
+        #[serde(default = "default_light_dir")]
+        dir: [f32; 3],
+        #[serde(default = "default_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AreaLightDescription {
+    shape: LightShapeDescription,
+    /// emitted radiance
+    #[serde(default = "default_color")]
+    color: [f32; 3],
+    /// shadow rays per shaded point
+    #[serde(default = "default_light_samples")]
+    samples: u32,
+}
+
+/// Either a single image with the faces in a cross layout or one image per face, see `CubeMapDescription::images`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeMapDescription {
+    cross: Option<PathBuf>,
+    right: Option<PathBuf>,
+    left: Option<PathBuf>,
+    top: Option<PathBuf>,
+    bottom: Option<PathBuf>,
+    front: Option<PathBuf>,
+    back: Option<PathBuf>,
+}
+
+/// The images of a cube map that were given.
+enum CubeMapImages<'a> {
+    Cross(&'a Path),
+    /// in the order right, left, top, bottom, front, back
+    Faces([&'a Path; 6]),
+}
+
+impl CubeMapDescription {
+    /// The cross or all six faces, anything else is an error that names the keys to add or remove.
+    fn images(&self) -> Result<CubeMapImages<'_>, SceneLoadError> {
+        let faces = [
+            ("right", &self.right),
+            ("left", &self.left),
+            ("top", &self.top),
+            ("bottom", &self.bottom),
+            ("front", &self.front),
+            ("back", &self.back),
+        ];
+        let names = |given: bool| -> Vec<&str> {
+            faces.iter().filter(|(_, path)| path.is_some() == given).map(|(name, _)| *name).collect()
+        };
+        let (given, missing) = (names(true), names(false));
+        let reason = match &self.cross {
+            Some(cross) if given.is_empty() => return Ok(CubeMapImages::Cross(cross)),
+            Some(_) => format!("cube_map takes `cross` or the six faces, not both (found `cross` and `{}`)", given.join("`, `")),
+            None if missing.is_empty() => return Ok(CubeMapImages::Faces(faces.map(|(_, path)| path.as_deref().unwrap()))),
+            None if given.is_empty() => "cube_map needs `cross` or the faces `right`, `left`, `top`, `bottom`, `front` and `back`".to_string(),
+            None => format!("cube_map is missing the faces `{}`", missing.join("`, `")),
+        };
+        Err(SceneLoadError::Invalid { reason })
+    }
+}
+
+/// Equirectangular `.hdr` or `.exr` image around the scene.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentDescription {
+    path: PathBuf,
+    /// around the y axis in degrees
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+    /// shadow rays per shaded point
+    #[serde(default = "default_light_samples")]
+    samples: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightShapeDescription {
+    Sphere { center: [f32; 3], radius: f32 },
+    Rect { center: [f32; 3], u: [f32; 3], v: [f32; 3] },
+    Disk { center: [f32; 3], normal: [f32; 3], radius: f32 },
+}
+
+fn default_texture_size() -> u32 {
+    1024
+}
+
+fn default_zoom() -> f32 {
+    1.5
+}
+
+fn default_screen_dist() -> f32 {
+    4.0
+}
+
+fn default_camera_dir() -> [f32; 3] {
+    [0.0, 0.0, 1.0]
+}
+
+fn default_camera_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_light_samples() -> u32 {
+    16
+}
+
+fn default_focus_dist() -> f32 {
+    20.0
+}
+
+fn default_ior() -> f32 {
+    1.5
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_shading() -> Shading {
+    Shading::Classic
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_light_dir() -> [f32; 3] {
+    [0.0, -1.0, 0.0]
+}
+
+fn default_wrap() -> WrapMode {
+    WrapMode::Repeat
+}
+
+fn default_texture_filter() -> TextureFilter {
+    TextureFilter::Bilinear
+}
+
+fn default_uv_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+fn default_normal_map_format() -> NormalMapFormat {
+    NormalMapFormat::OpenGl
+}
+
+fn default_bump_depth() -> f32 {
+    0.05
+}
+
+fn default_texture_space() -> TextureSpace {
+    TextureSpace::Object
+}
+
+fn default_pattern_scale() -> f32 {
+    1.0
+}
+
+fn default_octaves() -> u32 {
+    4
+}
+
+fn default_pattern_colors() -> [[f32; 3]; 2] {
+    [[0.0; 3], [1.0; 3]]
+}
+
+impl Scene {
+    /// Loads a scene from a TOML scene file, see `SceneDescription` for the format.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, SceneLoadError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|source| SceneLoadError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Scene::from_toml(&source, base_dir).map_err(|err| match err {
+            SceneLoadError::Parse { source, .. } => SceneLoadError::Parse {
+                path: path.to_path_buf(),
+                source,
+            },
+            err => err,
+        })
+    }
+
+    /// Builds a scene from the contents of a scene file. Relative texture paths are resolved against `base_dir`.
+    pub fn from_toml(source: &str, base_dir: &Path) -> Result<Scene, SceneLoadError> {
+        let description: SceneDescription = toml::from_str(source).map_err(|source| SceneLoadError::Parse {
+            path: PathBuf::new(),
+            source,
+        })?;
+
+        let skies: Vec<&str> = [
+            ("sky", description.sky.is_some()),
+            ("cube_map", description.cube_map.is_some()),
+            ("environment", description.environment.is_some()),
+        ]
+        .iter()
+        .filter(|(_, given)| *given)
+        .map(|(name, _)| *name)
+        .collect();
+        if skies.len() > 1 {
+            return Err(SceneLoadError::Invalid {
+                reason: format!("only one of `sky`, `cube_map` and `environment` can be given, found `{}`", skies.join("` and `")),
+            });
+        }
+
+        let mut scene = Scene::create_empty(800, 600);
+        scene.textures.target_width = description.texture_size;
+        if let Some(max_depth) = description.max_depth {
+            scene.max_depth = max_depth;
+        }
+        if let Some(integrator) = description.integrator {
+            scene.integrator = integrator;
+        }
+        if let Some(tone_mapping) = description.tone_mapping {
+            scene.tone_mapper.tone_mapping = tone_mapping;
+        }
+        scene.tone_mapper.exposure = description.exposure;
+        if let Some(samples_per_pixel) = description.samples_per_pixel {
+            scene.samples_per_pixel = samples_per_pixel;
+        }
+        if let Some(filter) = description.filter {
+            scene.filter = filter;
+        }
+        if let Some(glossy_samples) = description.glossy_samples {
+            scene.glossy_samples = glossy_samples;
+        }
+        if let Some(max_anisotropy) = description.max_anisotropy {
+            scene.max_anisotropy = max_anisotropy;
+        }
+        if let Some(camera) = &description.camera {
+            scene.camera = Camera {
+                org: Vec3A::from(camera.org),
+                orientation: Quat::IDENTITY,
+                zoom: camera.zoom,
+                screen_dist: camera.screen_dist,
+                aperture: camera.aperture,
+                focus_dist: camera.focus_dist,
+                bokeh: match camera.bokeh_blades {
+                    Some(blades) => Bokeh::Polygon { blades },
+                    None => Bokeh::Disk,
+                },
+                projection: camera.projection.unwrap_or(Projection::Perspective),
+            };
+            match camera.target {
+                Some(target) => scene.camera.look_at(Vec3A::from(target), Vec3A::from(camera.up)),
+                None => scene.camera.look_in(Vec3A::from(camera.dir), Vec3A::from(camera.up)),
+            }
+            scene.camera.roll(camera.roll.to_radians());
+        }
+        if let Some(sky) = &description.sky {
+            let sky = scene.textures.texture(base_dir.join(sky), true)?;
+            scene.set_sky(Some(sky));
+        }
+        if let Some(cube_map) = &description.cube_map {
+            let (path, cube_map) = match cube_map.images()? {
+                CubeMapImages::Cross(cross) => {
+                    let path = base_dir.join(cross);
+                    // a cross is 4 faces wide, the faces get at most `texture_size` pixels
+                    let image = scene.textures.image(&path, description.texture_size * 4)?;
+                    (path, CubeMap::from_cross(image))
+                }
+                CubeMapImages::Faces(paths) => {
+                    let mut face = |i: usize| scene.textures.image(base_dir.join(paths[i]), description.texture_size);
+                    let faces = [face(0)?, face(1)?, face(2)?, face(3)?, face(4)?, face(5)?];
+                    // blame the first face that isn't a square the size of the right face
+                    let size = faces[0].width();
+                    let wrong = faces.iter().position(|face| face.dimensions() != (size, size)).unwrap_or(0);
+                    (base_dir.join(paths[wrong]), CubeMap::create(faces))
+                }
+            };
+            scene.set_cube_map(cube_map.map_err(|source| SceneLoadError::Texture { path, source })?);
+        }
+        if let Some(environment) = &description.environment {
+            let path = base_dir.join(&environment.path);
+            let map = EnvironmentMap::load(&path, environment.rotation.to_radians(), environment.intensity, environment.samples)
+                .map_err(|source| SceneLoadError::Texture { path, source })?;
+            scene.set_environment(map);
+        }
+
+        let textures = &mut scene.textures;
+        let mut load_map = |texture: &Option<TextureDescription>, srgb: bool| -> Result<Option<Box<Texture>>, SceneLoadError> {
+            match texture {
+                Some(TextureDescription::Path(path)) => Ok(Some(Box::new(textures.texture(base_dir.join(path), srgb)?))),
+                Some(TextureDescription::Settings(settings)) => {
+                    let mut texture = textures.texture(base_dir.join(&settings.path), srgb)?;
+                    texture.wrap = settings.wrap;
+                    texture.filter = settings.filter;
+                    texture.transform = UvTransform {
+                        scale: Vec2::from(settings.scale),
+                        rotation: settings.rotation.to_radians(),
+                        offset: Vec2::from(settings.offset),
+                    };
+                    Ok(Some(Box::new(texture)))
+                }
+                Some(TextureDescription::Procedural(procedural)) => Ok(Some(Box::new(Texture::procedural(Procedural {
+                    pattern: procedural.pattern,
+                    space: procedural.space,
+                    scale: procedural.scale,
+                    octaves: procedural.octaves,
+                    colors: procedural.colors.map(Vec3A::from),
+                })))),
+                None => Ok(None),
+            }
+        };
+        let mut materials = HashMap::new();
+        for (name, mat) in description.materials.iter() {
+            let texture = load_map(&mat.texture, true)?;
+            let normal_map = load_map(&mat.normal_map, false)?;
+            let emissive_map = load_map(&mat.emissive_map, true)?;
+            let emissive = match (mat.emissive, &emissive_map) {
+                (Some(emissive), _) => Vec3A::from(emissive),
+                (None, Some(_)) => Vec3A::ONE,
+                (None, None) => Vec3A::ZERO,
+            };
+            let metallic_map = load_map(&mat.metallic_map, false)?;
+            let roughness_map = load_map(&mat.roughness_map, false)?;
+            // a map alone gives the values, like the factors of glTF
+            let factor = |value: Option<f32>, map: &Option<Box<Texture>>| value.unwrap_or(if map.is_some() { 1.0 } else { 0.0 });
+            materials.insert(name.as_str(), Arc::new(Material {
+                color: Vec3A::from(mat.color),
+                reflect: mat.reflect,
+                ior: mat.ior,
+                transparency: mat.transparency,
+                texture,
+                normal_map,
+                normal_map_format: mat.normal_map_format,
+                bump_map: load_map(&mat.bump_map, false)?,
+                bump_depth: mat.bump_depth,
+                shading: mat.shading,
+                metallic: factor(mat.metallic, &metallic_map),
+                roughness: factor(mat.roughness, &roughness_map),
+                metallic_map,
+                roughness_map,
+                occlusion_map: load_map(&mat.occlusion_map, false)?,
+                emissive,
+                emissive_intensity: mat.emissive_intensity,
+                emissive_map,
+            }));
+        }
+
+        let find_material = |name: &str| -> Result<Arc<Material>, SceneLoadError> {
+            materials.get(name).cloned().ok_or_else(|| SceneLoadError::UnknownMaterial {
+                name: name.to_string(),
+            })
+        };
+
+        for sphere in description.spheres.iter() {
+            let mat = find_material(&sphere.material)?;
+            scene.add_sphere(Sphere::create(Vec3A::from(sphere.center), sphere.radius, mat));
+        }
+
+        for plane in description.planes.iter() {
+            let mat = find_material(&plane.material)?;
+            scene.add_object(Plane::create(Vec3A::from(plane.point), Vec3A::from(plane.normal), plane.texture_scale, mat));
+        }
+
+        for aa_box in description.boxes.iter() {
+            let mat = find_material(&aa_box.material)?;
+            scene.add_object(AxisAlignedBox::create(Vec3A::from(aa_box.min), Vec3A::from(aa_box.max), aa_box.texture_scale, mat));
+        }
+
+        for mesh in description.meshes.iter() {
+            let transform = Affine3A::from_scale_rotation_translation(Vec3::splat(mesh.scale), Quat::IDENTITY, Vec3::from(mesh.position));
+            let default_mat = Material::create(Vec3A::splat(0.8), 0.0);
+            let mut triangles = load_obj(base_dir.join(&mesh.path), &mut scene.textures, &transform, default_mat)
+                .map_err(|source| SceneLoadError::Mesh { source })?;
+            if let Some(name) = &mesh.material {
+                let mat = find_material(name)?;
+                for triangle in triangles.iter_mut() {
+                    triangle.mat = mat.clone();
+                }
+            }
+            scene.add_mesh(triangles);
+        }
+
+        for light in description.lights.iter() {
+            match *light {
+                LightDescription::Point { org, color, intensity } => scene.add_light(PointLight {
+                    org: Vec3A::from(org),
+                    color: Vec3A::from(color) * intensity,
+                }),
+                LightDescription::Spot { org, dir, inner_angle, outer_angle, color, intensity } => scene.add_light(SpotLight {
+                    org: Vec3A::from(org),
+                    dir: Vec3A::from(dir).normalize(),
+                    inner_angle: inner_angle.min(outer_angle).to_radians(),
+                    outer_angle: outer_angle.to_radians(),
+                    color: Vec3A::from(color) * intensity,
+                }),
+                LightDescription::Directional { dir, color, intensity } => scene.add_light(DirectionalLight {
+                    dir: Vec3A::from(dir).normalize(),
+                    color: Vec3A::from(color) * intensity,
+                }),
+            }
+        }
+        for light in description.area_lights.iter() {
+            let shape = match light.shape {
+                LightShapeDescription::Sphere { center, radius } => LightShape::Sphere { center: Vec3A::from(center), radius },
+                LightShapeDescription::Rect { center, u, v } => LightShape::Rect {
+                    center: Vec3A::from(center),
+                    u: Vec3A::from(u),
+                    v: Vec3A::from(v),
+                },
+                LightShapeDescription::Disk { center, normal, radius } => LightShape::Disk {
+                    center: Vec3A::from(center),
+                    normal: Vec3A::from(normal).normalize(),
+                    radius,
+                },
+            };
+            scene.add_area_light(AreaLight::create(shape, Vec3A::from(light.color), light.samples));
+        }
+
+        Ok(scene)
+    }
+}
@@ -1,60 +1,214 @@
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use glam::{Vec2, Vec3A};
 use image::imageops::FilterType;
-use image::{GenericImageView, RgbImage};
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ImageError, RgbImage};
 use image::io::Reader as ImageReader;
+use serde::Deserialize;
+use crate::color::srgb_to_linear;
+use crate::scene::mip_map::{Footprint, MipMap};
+use crate::scene::procedural::Procedural;
 
-pub fn get_pixel(ref img: &RgbImage, pos: &Vec2) -> Vec3A {
-    // Bilinear interpolation
-    let iw = img.width();
-    let ih = img.height();
-    let w = iw as f32;
-    let h = ih as f32;
-    let xf = pos.x * w;
-    assert!(xf >= 0.0, "xf should not underflow 0");
-    let yf = pos.y * h;
-    assert!(yf >= 0.0, "yf should not underflow 0");
-    let x0 = xf as u32;
-    let y0 = yf as u32;
-    let x1 = (x0 + 1) % iw;
-    let y1 = (y0 + 1) % ih;
-
-    let top_left = img.get_pixel(x0, y0);
-    let top_right = img.get_pixel(x1, y0);
-    let bot_left = img.get_pixel(x0, y1);
-    let bot_right = img.get_pixel(x1, y1);
-
-    let dx0 = xf - x0 as f32;
-    let dy0 = yf - y0 as f32;
-    let dx1 = 1.0 - dx0;
-    let dy1 = 1.0 - dy0;
-
-    let mut res = Vec3A::ZERO;
-    for dim in 0..3 {
-        let top = top_left[dim] as f32 * dx1 / 255.0 + top_right[dim] as f32 * dx0 / 255.0;
-        let bot = bot_left[dim] as f32 * dx1 / 255.0 + bot_right[dim] as f32 * dx0 / 255.0;
-        res[dim] = top * dy1 + bot * dy0;
-    }
-
-    res
-}
-
-pub fn load_texture(path: &str, target_width: u32) -> RgbImage {
-    let l = ImageReader::open(path);
-    if l.is_err() {
-        panic!("Problem loading the file: {:?}", l.err());
-    }
-    let decoded = l.unwrap().decode();
-    if decoded.is_err() {
-        panic!("Problem loading the file: {:?}", decoded.err());
-    }
-    let unwrapped = decoded.unwrap();
+/// What a texture shows outside of [0, 1).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    /// tiles the texture
+    Repeat,
+    /// continues the edge texels
+    Clamp,
+    /// tiles the texture, every other tile mirrored so the tiles meet without seams
+    Mirror,
+}
+
+impl WrapMode {
+    /// texel index inside an image with `size` texels for any index `i`
+    fn wrap(&self, i: i64, size: u32) -> u32 {
+        let size = size as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Clamp => i.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * size);
+                if i < size { i } else { 2 * size - 1 - i }
+            }
+        };
+        i as u32
+    }
+}
+
+/// How the texels around a lookup position are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureFilter {
+    /// the texel under the position, for pixel art
+    Nearest,
+    /// blend of the 4 closest texels
+    Bilinear,
+}
+
+/// Maps the texture coordinates of an object to the texture like glTF's `KHR_texture_transform`: scaled first, then
+/// rotated counter-clockwise around the origin and moved by `offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvTransform {
+    pub scale: Vec2,
+    /// in radians
+    pub rotation: f32,
+    pub offset: Vec2,
+}
+
+impl UvTransform {
+    pub fn apply(&self, uv: Vec2) -> Vec2 {
+        self.apply_linear(uv) + self.offset
+    }
+
+    /// transforms a difference of texture coordinates, the offset doesn't apply
+    fn apply_linear(&self, d: Vec2) -> Vec2 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let d = d * self.scale;
+        Vec2::new(cos * d.x + sin * d.y, -sin * d.x + cos * d.y)
+    }
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        UvTransform {
+            scale: Vec2::ONE,
+            rotation: 0.0,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+/// Image or procedural pattern on the surface of a material, with the settings for looking it up. Any texture
+/// coordinate is valid, the wrap mode decides what is outside of [0, 1).
+pub struct Texture {
+    source: Source,
+    pub wrap: WrapMode,
+    pub filter: TextureFilter,
+    /// moves images and patterns in `Uv` space, patterns in object space ignore it
+    pub transform: UvTransform,
+}
+
+enum Source {
+    Image(Arc<MipMap>),
+    Procedural(Procedural),
+}
+
+impl Texture {
+    /// Repeating, bilinear texture without a transform. `srgb` for color textures, see `MipMap::create`.
+    pub fn create(image: RgbImage, srgb: bool) -> Texture {
+        Texture::shared(Arc::new(MipMap::create(image, srgb)))
+    }
+
+    /// Texture of an image that other textures use as well, e.g. from a `TextureCache`.
+    pub fn shared(mip_map: Arc<MipMap>) -> Texture {
+        Texture::with_source(Source::Image(mip_map))
+    }
+
+    /// Texture computed from the position, it needs no image.
+    pub fn procedural(procedural: Procedural) -> Texture {
+        Texture::with_source(Source::Procedural(procedural))
+    }
+
+    fn with_source(source: Source) -> Texture {
+        Texture {
+            source,
+            wrap: WrapMode::Repeat,
+            filter: TextureFilter::Bilinear,
+            transform: UvTransform::default(),
+        }
+    }
+
+    /// computed from the position instead of read from an image
+    pub fn is_procedural(&self) -> bool {
+        matches!(self.source, Source::Procedural(_))
+    }
+
+    /// Raw texel values in [0, 1] averaged over `footprint`, for data like normal maps. Procedural textures are
+    /// evaluated at `object_coord`, the position relative to the object, if they are in object space.
+    pub fn get_pixel(&self, pos: &Vec2, object_coord: &Vec3A, footprint: &Footprint) -> Vec3A {
+        self.sample(pos, object_coord, footprint, |c| c as f32 / 255.0)
+    }
+
+    /// Linear color averaged over `footprint`, for sRGB encoded color textures. The colors of procedural textures
+    /// are linear already.
+    pub fn get_color(&self, pos: &Vec2, object_coord: &Vec3A, footprint: &Footprint) -> Vec3A {
+        self.sample(pos, object_coord, footprint, srgb_texel)
+    }
+
+    fn sample(&self, pos: &Vec2, object_coord: &Vec3A, footprint: &Footprint, texel: fn(u8) -> f32) -> Vec3A {
+        let pos = self.transform.apply(*pos);
+        let mip_map = match &self.source {
+            Source::Image(mip_map) => mip_map,
+            Source::Procedural(procedural) => return procedural.color(pos, *object_coord),
+        };
+        let footprint = Footprint {
+            dx: self.transform.apply_linear(footprint.dx),
+            dy: self.transform.apply_linear(footprint.dy),
+            ..*footprint
+        };
+        mip_map.sample(pos, &footprint, |image, pos| match self.filter {
+            TextureFilter::Nearest => sample_nearest(image, pos, self.wrap, texel),
+            TextureFilter::Bilinear => sample_bilinear(image, pos, self.wrap, texel),
+        })
+    }
+}
+
+/// linear value of an sRGB encoded texel channel
+pub(crate) fn srgb_texel(c: u8) -> f32 {
+    static SRGB_TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    let table = SRGB_TABLE.get_or_init(|| {
+        let mut table = [0.0; 256];
+        for (i, value) in table.iter_mut().enumerate() {
+            *value = srgb_to_linear(i as f32 / 255.0);
+        }
+        table
+    });
+    table[c as usize]
+}
+
+fn decode<F: Fn(u8) -> f32>(img: &RgbImage, x: u32, y: u32, texel: F) -> Vec3A {
+    let pixel = img.get_pixel(x, y);
+    Vec3A::new(texel(pixel[0]), texel(pixel[1]), texel(pixel[2]))
+}
+
+fn sample_nearest<F: Fn(u8) -> f32>(img: &RgbImage, pos: Vec2, wrap: WrapMode, texel: F) -> Vec3A {
+    let x = wrap.wrap((pos.x * img.width() as f32).floor() as i64, img.width());
+    let y = wrap.wrap((pos.y * img.height() as f32).floor() as i64, img.height());
+    decode(img, x, y, texel)
+}
+
+/// Bilinear interpolation between the centers of the 4 closest texels.
+fn sample_bilinear<F: Fn(u8) -> f32>(img: &RgbImage, pos: Vec2, wrap: WrapMode, texel: F) -> Vec3A {
+    let xf = pos.x * img.width() as f32 - 0.5;
+    let yf = pos.y * img.height() as f32 - 0.5;
+    let (x0, y0) = (xf.floor(), yf.floor());
+    let (dx, dy) = (xf - x0, yf - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let (x1, y1) = (wrap.wrap(x0 + 1, img.width()), wrap.wrap(y0 + 1, img.height()));
+    let (x0, y0) = (wrap.wrap(x0, img.width()), wrap.wrap(y0, img.height()));
+
+    let top = decode(img, x0, y0, &texel) * (1.0 - dx) + decode(img, x1, y0, &texel) * dx;
+    let bottom = decode(img, x0, y1, &texel) * (1.0 - dx) + decode(img, x1, y1, &texel) * dx;
+    top * (1.0 - dy) + bottom * dy
+}
+
+/// Loads an image, downsized to at most `target_width` pixels wide unless it is 0. See `TextureCache` for loading every
+/// file once.
+pub fn try_load_texture<P: AsRef<Path>>(path: P, target_width: u32) -> Result<RgbImage, ImageError> {
+    let unwrapped = ImageReader::open(path)?.decode()?;
+    if unwrapped.width() == 0 || unwrapped.height() == 0 {
+        return Err(ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch)));
+    }
     if target_width > 0 {
         let w = unwrapped.width();
         let h = unwrapped.height();
         let resized_width = std::cmp::min(target_width, w);
-        let resized_height = resized_width * h / w;
-        return unwrapped.resize(resized_width, resized_height, FilterType::Gaussian).to_rgb8()
+        // wide images would lose their last row otherwise
+        let resized_height = (resized_width * h / w).max(1);
+        return Ok(unwrapped.resize(resized_width, resized_height, FilterType::Gaussian).to_rgb8())
     }
-    unwrapped.to_rgb8()
+    Ok(unwrapped.to_rgb8())
 }
-
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use image::{ImageError, RgbImage};
+use crate::scene::mip_map::MipMap;
+use crate::scene::texture::{try_load_texture, Texture};
+
+/// Error returned by `TextureCache::load`.
+#[derive(Debug)]
+pub struct TextureLoadError {
+    pub path: PathBuf,
+    /// the file could not be read or decoded
+    pub source: ImageError,
+}
+
+impl fmt::Display for TextureLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not load texture {}: {}", self.path.display(), self.source)
+    }
+}
+
+impl Error for TextureLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Loads every image file once. Textures of the same file share its mip map, whatever their wrap mode, filter or
+/// transform.
+pub struct TextureCache {
+    /// images wider than this are downsized when loading, 0 keeps the original size
+    pub target_width: u32,
+    /// by canonical path and whether the image is sRGB, color and data textures build different mip maps
+    mip_maps: HashMap<(PathBuf, bool), Arc<MipMap>>,
+    /// images without mip maps like the faces of a cube map, by canonical path and the width they were downsized to
+    images: HashMap<(PathBuf, u32), Arc<RgbImage>>,
+}
+
+/// different spellings of a path find the same file
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn load_error(path: &Path, source: ImageError) -> TextureLoadError {
+    TextureLoadError {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+impl TextureCache {
+    pub fn create(target_width: u32) -> TextureCache {
+        TextureCache {
+            target_width,
+            mip_maps: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    /// Mip map of the image at `path`, loaded on the first request of the path. `srgb` for color textures.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P, srgb: bool) -> Result<Arc<MipMap>, TextureLoadError> {
+        let path = path.as_ref();
+        let key = (canonical(path), srgb);
+        if let Some(mip_map) = self.mip_maps.get(&key) {
+            return Ok(mip_map.clone());
+        }
+        let image = try_load_texture(path, self.target_width).map_err(|source| load_error(path, source))?;
+        let mip_map = Arc::new(MipMap::create(image, srgb));
+        self.mip_maps.insert(key, mip_map.clone());
+        Ok(mip_map)
+    }
+
+    /// Image at `path` without mip maps, downsized if it is wider than `target_width` (0 keeps the original size).
+    /// Loaded on the first request of the path and width.
+    pub fn image<P: AsRef<Path>>(&mut self, path: P, target_width: u32) -> Result<Arc<RgbImage>, TextureLoadError> {
+        let path = path.as_ref();
+        let key = (canonical(path), target_width);
+        if let Some(image) = self.images.get(&key) {
+            return Ok(image.clone());
+        }
+        let image = Arc::new(try_load_texture(path, target_width).map_err(|source| load_error(path, source))?);
+        self.images.insert(key, image.clone());
+        Ok(image)
+    }
+
+    /// Repeating, bilinear texture of the image at `path`, see `load`. `srgb` for color textures, the other maps hold
+    /// data. Textures of the same file share its image.
+    pub fn texture<P: AsRef<Path>>(&mut self, path: P, srgb: bool) -> Result<Texture, TextureLoadError> {
+        self.load(path, srgb).map(Texture::shared)
+    }
+
+    /// number of loaded images
+    pub fn len(&self) -> usize {
+        self.mip_maps.len() + self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// bytes taken by the loaded images and their mip maps
+    pub fn memory(&self) -> usize {
+        let mip_maps: usize = self.mip_maps.values().map(|mip_map| mip_map.memory()).sum();
+        mip_maps + self.images.values().map(|image| image.as_raw().len()).sum::<usize>()
+    }
+}
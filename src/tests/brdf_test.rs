@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod brdf_test {
+    use std::f32::consts::PI;
+    use glam::Vec3A;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::scene::brdf::{glossy_reflection, Microfacet};
+    use crate::scene::sampling::orthonormal_basis;
+
+    fn materials() -> Vec<Microfacet> {
+        let mut materials = Vec::new();
+        for metallic in [0.0, 1.0] {
+            for roughness in [0.4, 0.7, 1.0] {
+                materials.push(Microfacet { base_color: Vec3A::new(0.9, 0.6, 0.3), metallic, roughness });
+            }
+        }
+        materials
+    }
+
+    #[test]
+    fn brdf_is_reciprocal() {
+        let normal = Vec3A::new(0.2, 1.0, -0.1).normalize();
+        let a = Vec3A::new(0.7, 0.5, 0.1).normalize();
+        let b = Vec3A::new(-0.3, 0.8, 0.4).normalize();
+        for brdf in materials() {
+            let ab = brdf.eval(normal, a, b) / normal.dot(b);
+            let ba = brdf.eval(normal, b, a) / normal.dot(a);
+            assert!((ab - ba).abs().max_element() < 1e-4, "{:?} != {:?}", ab, ba);
+        }
+    }
+
+    #[test]
+    fn sampling_agrees_with_evaluation() {
+        // the reflected fraction of light from a uniform environment, estimated through both functions
+        let normal = Vec3A::Y;
+        let to_viewer = Vec3A::new(0.5, 0.8, 0.0).normalize();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        let mut rng = StdRng::seed_from_u64(17);
+        let n = 100_000;
+        for brdf in materials() {
+            let mut uniform = Vec3A::ZERO;
+            let mut sampled = Vec3A::ZERO;
+            for _ in 0..n {
+                // uniform hemisphere, density 1 / 2pi
+                let cos: f32 = rng.gen();
+                let sin = (1.0 - cos * cos).sqrt();
+                let phi = 2.0 * PI * rng.gen::<f32>();
+                let dir = tangent * (sin * phi.cos()) + bitangent * (sin * phi.sin()) + normal * cos;
+                uniform += brdf.eval(normal, to_viewer, dir) * 2.0 * PI;
+                if let Some(sample) = brdf.sample(normal, to_viewer, &mut rng) {
+                    sampled += sample.weight;
+                }
+            }
+            let (uniform, sampled) = (uniform / n as f32, sampled / n as f32);
+            assert!(sampled.max_element() <= 1.0, "{:?}", sampled);
+            assert!((uniform - sampled).abs().max_element() < 0.02, "{} {}: {:?} != {:?}", brdf.metallic, brdf.roughness, uniform, sampled);
+        }
+    }
+
+    #[test]
+    fn glossy_reflections_spread_with_roughness() {
+        let normal = Vec3A::Y;
+        let dir = Vec3A::new(0.3, -1.0, 0.0).normalize();
+        let mirror = Vec3A::new(0.3, 1.0, 0.0).normalize();
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut spread = |roughness: f32| {
+            let mut mean = Vec3A::ZERO;
+            for _ in 0..10_000 {
+                let reflection = glossy_reflection(dir, normal, roughness, &mut rng);
+                assert!((reflection.length() - 1.0).abs() < 1e-4);
+                mean += reflection;
+            }
+            // the lobe is centered on the mirror direction, the mean gets shorter the wider the lobe
+            let mean = mean / 10_000.0;
+            assert!(mean.normalize().dot(mirror) > 0.95, "{}", roughness);
+            mean.length()
+        };
+        let (smooth, medium, rough) = (spread(0.05), spread(0.3), spread(0.8));
+        assert!(smooth > 0.99);
+        assert!(smooth > medium && medium > rough, "{} {} {}", smooth, medium, rough);
+    }
+}
\ No newline at end of file
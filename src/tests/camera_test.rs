@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod camera_test {
+    use std::f32::consts::PI;
+    use glam::{Quat, Vec2, Vec3A};
+    use crate::scene::camera::{Bokeh, Camera, Projection};
+
+    fn camera() -> Camera {
+        Camera {
+            org: Vec3A::new(0.0, 5.0, -5.0),
+            orientation: Quat::IDENTITY,
+            zoom: 1.5,
+            screen_dist: 4.0,
+            aperture: 0.0,
+            focus_dist: 20.0,
+            bokeh: Bokeh::Disk,
+            projection: Projection::Perspective,
+        }
+    }
+
+    #[test]
+    fn look_at_builds_an_orthonormal_basis() {
+        let mut camera = camera();
+        camera.look_at(Vec3A::new(3.0, 1.0, 7.0), Vec3A::Y);
+        let dir = camera.dir();
+        assert!(dir.abs_diff_eq((Vec3A::new(3.0, 1.0, 7.0) - camera.org).normalize(), 1e-5));
+        assert!(camera.up().dot(dir).abs() < 1e-5);
+        assert!(camera.right().dot(dir).abs() < 1e-5);
+        // no roll: the right direction stays horizontal
+        assert!(camera.right().y.abs() < 1e-5);
+        assert!(camera.up().y > 0.0);
+
+        // the center pixel looks along the view direction
+        let ray = camera.primary_ray(64, 48, 32.0, 24.0, Vec2::ZERO);
+        assert!(ray.dir.abs_diff_eq(dir, 1e-5));
+    }
+
+    #[test]
+    fn looking_straight_down_keeps_a_valid_view() {
+        let mut camera = camera();
+        camera.look_in(-Vec3A::Y, Vec3A::Y);
+        assert!(camera.dir().abs_diff_eq(-Vec3A::Y, 1e-5));
+        // the previous up direction becomes the top of the screen
+        assert!(camera.up().abs_diff_eq(Vec3A::Z, 1e-5));
+        let ray = camera.primary_ray(64, 48, 10.0, 5.0, Vec2::ZERO);
+        assert!(ray.dir.is_finite() && ray.org.is_finite());
+    }
+
+    #[test]
+    fn roll_tilts_the_screen() {
+        let mut camera = camera();
+        let right = camera.right();
+        camera.roll(PI / 2.0);
+        assert!(camera.up().abs_diff_eq(right, 1e-5));
+        assert!(camera.dir().abs_diff_eq(Vec3A::Z, 1e-5));
+    }
+
+    #[test]
+    fn turn_clamps_the_pitch() {
+        let mut camera = camera();
+        // far more than a quarter turn upwards
+        for _ in 0..100 {
+            camera.turn(0, 100);
+        }
+        let dir = camera.dir();
+        assert!(dir.y > 0.99 && dir.y < 1.0);
+        // still looking forward, not flipped over backwards
+        assert!(dir.z > 0.0);
+        assert!(camera.up().z < 0.0);
+
+        camera.turn(1000, -500);
+        assert!((camera.dir().length() - 1.0).abs() < 1e-5);
+        assert!(camera.right().y.abs() < 1e-4);
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod color_test {
+    use glam::Vec3A;
+    use crate::color::{linear_to_srgb, srgb_to_linear, ToneMapper, ToneMapping};
+    use crate::frame_buffer::create_frame_buffer;
+
+    #[test]
+    fn srgb_conversion_round_trips() {
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-5);
+        }
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        // linear middle gray is much brighter when encoded
+        assert!((linear_to_srgb(0.5) - 0.7354).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tone_mapping_keeps_colors_displayable() {
+        let bright = Vec3A::new(0.5, 4.0, 100.0);
+        for tone_mapping in [ToneMapping::Clamp, ToneMapping::Reinhard, ToneMapping::AcesFilmic] {
+            let mapped = tone_mapping.apply(bright);
+            assert!(mapped.max_element() <= 1.0 && mapped.min_element() >= 0.0);
+            assert!(mapped.x < mapped.y, "{:?} does not preserve the order", tone_mapping);
+        }
+        assert_eq!(ToneMapping::Reinhard.apply(Vec3A::ONE), Vec3A::splat(0.5));
+        assert_eq!(ToneMapping::Clamp.apply(bright), Vec3A::new(0.5, 1.0, 1.0));
+        // only the compressing operators keep detail in the highlights
+        assert!(ToneMapping::AcesFilmic.apply(bright).y < 1.0);
+    }
+
+    #[test]
+    fn frame_buffer_writes_exposed_srgb_bytes() {
+        let mut frame = create_frame_buffer(2, 1);
+        frame.set_pixel(0, 0, Vec3A::splat(0.5));
+        frame.set_pixel(1, 0, Vec3A::splat(0.25));
+        frame.samples = 1;
+
+        let mut screen = [0u8; 8];
+        frame.write_rgba(&mut screen, &ToneMapper::default());
+        assert_eq!(screen, [188, 188, 188, 255, 137, 137, 137, 255]);
+
+        // one stop more exposure doubles the linear color
+        let brighter = ToneMapper { exposure: 1.0, ..ToneMapper::default() };
+        frame.write_rgba(&mut screen, &brighter);
+        assert_eq!(&screen[4..], [188, 188, 188, 255]);
+        assert_eq!(frame.to_linear_rgba(&brighter), vec![1.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 1.0]);
+    }
+}
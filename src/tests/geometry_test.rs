@@ -1,10 +1,19 @@
 #[cfg(test)]
 mod geometry_test {
-    use glam::Vec3A;
+    use std::fs;
+    use glam::{Affine3A, Vec2, Vec3A};
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::geometry::axis_aligned_box::AxisAlignedBox;
+    use crate::geometry::bvh::Bvh;
+    use crate::geometry::plane::Plane;
     use crate::geometry::ray::Ray;
     use crate::geometry::sphere::Sphere;
-    use crate::geometry::traceable::Traceable;
+    use crate::geometry::traceable::{tangent_frame, Traceable};
+    use crate::geometry::triangle::Triangle;
     use crate::scene::material::Material;
+    use crate::scene::obj_loader::load_obj;
+    use crate::scene::texture_cache::TextureCache;
 
     #[test]
     fn sphere_intersects_ray() {
@@ -21,4 +30,206 @@ mod geometry_test {
         assert_eq!(t > 0.0, true);
         assert_eq!(intersection_point.length() > 0.0, true);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bvh_finds_the_closest_hit() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mat = Material::create(Vec3A::ONE, 0.1);
+        let mut objects: Vec<Box<dyn Traceable>> = Vec::new();
+        for _ in 0..200 {
+            let center = Vec3A::new(rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0));
+            objects.push(Box::new(Sphere::create(center, rng.gen_range(0.1..2.0), mat.clone())));
+        }
+        let mut bvh = Bvh::build(&objects);
+        for object in objects.iter_mut() {
+            object.update();
+        }
+        bvh.refit(&objects);
+
+        for _ in 0..500 {
+            let ray = Ray {
+                org: Vec3A::new(rng.gen_range(-30.0..30.0), rng.gen_range(-30.0..30.0), -30.0),
+                dir: Vec3A::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 1.0).normalize(),
+            };
+            let mut expected: Option<(f32, usize)> = None;
+            for (i, object) in objects.iter().enumerate() {
+                let mut t: f32 = 0.0;
+                if object.intersect(&ray, &mut t) && expected.is_none_or(|(t_min, _)| t < t_min) {
+                    expected = Some((t, i));
+                }
+            }
+            assert_eq!(bvh.intersect(&objects, &ray, 0.0, f32::MAX), expected);
+            assert_eq!(bvh.occluded(&objects, &ray, 0.0, f32::MAX), expected.is_some());
+        }
+    }
+
+    #[test]
+    fn triangle_intersects_ray() {
+        let triangle = Triangle::create(
+            [Vec3A::new(-1.0, -1.0, 5.0), Vec3A::new(1.0, -1.0, 5.0), Vec3A::new(-1.0, 1.0, 5.0)],
+            None,
+            Some([Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)]),
+            Material::create(Vec3A::ONE, 0.1),
+        );
+        let ray = Ray {
+            org: Vec3A::new(-0.5, -0.5, 0.0),
+            dir: Vec3A::Z,
+        };
+        let mut t: f32 = 0.0;
+        assert!(triangle.intersect(&ray, &mut t));
+        assert!((t - 5.0).abs() < 1e-5);
+
+        let (normal, reflection) = triangle.intersection_normal(&ray, ray.point_at(t));
+        assert!((normal.z.abs() - 1.0).abs() < 1e-5);
+        assert!((reflection + Vec3A::Z).length() < 1e-5);
+        let uv = triangle.get_texture_coord(&ray.point_at(t));
+        assert!((uv - Vec2::new(0.25, 0.25)).length() < 1e-5);
+
+        let miss = Ray {
+            org: Vec3A::new(0.5, 0.5, 0.0),
+            dir: Vec3A::Z,
+        };
+        assert!(!triangle.intersect(&miss, &mut t));
+    }
+
+    #[test]
+    fn plane_tiles_texture_coordinates() {
+        let plane = Plane::create(Vec3A::new(0.0, -3.0, 0.0), Vec3A::Y, 2.0, Material::create(Vec3A::ONE, 0.1));
+        let ray = Ray {
+            org: Vec3A::new(-5.0, 2.0, 7.0),
+            dir: Vec3A::new(0.0, -1.0, 1.0).normalize(),
+        };
+        let mut t: f32 = 0.0;
+        assert!(plane.intersect(&ray, &mut t));
+        let hit = ray.point_at(t);
+        assert!((hit.y + 3.0).abs() < 1e-4);
+
+        let (normal, reflection) = plane.intersection_normal(&ray, hit);
+        assert_eq!(normal, Vec3A::Y);
+        assert!((reflection - Vec3A::new(0.0, 1.0, 1.0).normalize()).length() < 1e-5);
+
+        // one texture repetition further the coordinates differ by whole textures, the texture does the wrapping
+        let uv = plane.get_texture_coord(&hit);
+        let uv_next = plane.get_texture_coord(&(hit + Vec3A::new(2.0, 0.0, -4.0)));
+        assert!((uv_next - uv - Vec2::new(1.0, 2.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn box_intersects_ray() {
+        let aa_box = AxisAlignedBox::create(Vec3A::new(-1.0, -1.0, 4.0), Vec3A::new(1.0, 1.0, 6.0), 1.0, Material::create(Vec3A::ONE, 0.1));
+        let ray = Ray {
+            org: Vec3A::new(0.5, 0.0, 0.0),
+            dir: Vec3A::Z,
+        };
+        let mut t: f32 = 0.0;
+        assert!(aa_box.intersect(&ray, &mut t));
+        assert!((t - 4.0).abs() < 1e-5);
+        let (normal, _) = aa_box.intersection_normal(&ray, ray.point_at(t));
+        assert_eq!(normal, -Vec3A::Z);
+
+        // from the inside the far side is hit
+        let inside = Ray {
+            org: Vec3A::new(0.0, 0.0, 5.0),
+            dir: Vec3A::X,
+        };
+        assert!(aa_box.intersect(&inside, &mut t));
+        assert!((t - 1.0).abs() < 1e-5);
+        assert_eq!(aa_box.intersection_normal(&inside, inside.point_at(t)).0, Vec3A::X);
+    }
+
+    #[test]
+    fn flat_box_has_a_valid_normal() {
+        let slab = AxisAlignedBox::create(Vec3A::new(-1.0, 0.0, 4.0), Vec3A::new(1.0, 0.0, 6.0), 1.0, Material::create(Vec3A::ONE, 0.1));
+        let ray = Ray {
+            org: Vec3A::new(0.3, 2.0, 5.2),
+            dir: -Vec3A::Y,
+        };
+        let mut t: f32 = 0.0;
+        assert!(slab.intersect(&ray, &mut t));
+        let point = ray.point_at(t);
+        assert_eq!(slab.intersection_normal(&ray, point).0, Vec3A::Y);
+        assert!(slab.get_texture_coord(&point).is_finite());
+    }
+
+    #[test]
+    fn sphere_intersects_ray_from_inside() {
+        let sphere = Sphere::create(Vec3A::new(0.0, 0.0, 5.0), 2.0, Material::create(Vec3A::ONE, 0.1));
+        let ray = Ray {
+            org: Vec3A::new(0.0, 0.0, 5.0),
+            dir: Vec3A::X,
+        };
+        let mut t: f32 = 0.0;
+        assert!(sphere.intersect(&ray, &mut t));
+        assert!((t - 2.0).abs() < 1e-5);
+
+        let behind = Ray {
+            org: Vec3A::new(0.0, 0.0, 8.0),
+            dir: Vec3A::Z,
+        };
+        assert!(!sphere.intersect(&behind, &mut t));
+    }
+
+    #[test]
+    fn tangents_point_where_the_texture_coordinates_grow() {
+        let mat = Material::create(Vec3A::ONE, 0.1);
+        let uvs = Some([Vec2::new(0.2, 0.9), Vec2::new(0.3, 0.1), Vec2::new(0.8, 0.4)]);
+        let vertices = [Vec3A::new(0.0, 0.0, 5.0), Vec3A::new(1.0, 2.0, 5.5), Vec3A::new(3.0, 0.5, 4.0)];
+        let objects: Vec<(Box<dyn Traceable>, Vec3A)> = vec![
+            (Box::new(Sphere::create(Vec3A::new(1.0, 2.0, 3.0), 2.0, mat.clone())), Vec3A::new(2.0, 3.0, 1.6)),
+            (Box::new(Plane::create(Vec3A::ZERO, Vec3A::new(0.3, 1.0, -0.2), 4.0, mat.clone())), Vec3A::new(0.5, -0.1, 1.0)),
+            (Box::new(AxisAlignedBox::create(Vec3A::ZERO, Vec3A::splat(2.0), 4.0, mat.clone())), Vec3A::new(2.0, 0.7, 1.2)),
+            (Box::new(AxisAlignedBox::create(Vec3A::ZERO, Vec3A::splat(2.0), 4.0, mat.clone())), Vec3A::new(0.6, 2.0, 1.2)),
+            (Box::new(Triangle::create(vertices, None, uvs, mat)), Vec3A::new(1.4, 0.8, 4.9)),
+        ];
+        for (object, point) in objects.iter() {
+            let ray = Ray {
+                org: *point + Vec3A::new(0.0, 0.0, -20.0),
+                dir: Vec3A::Z,
+            };
+            let (normal, _) = object.intersection_normal(&ray, *point);
+            let (tangent, bitangent) = object.tangents(point, normal);
+            assert!((tangent.length() - 1.0).abs() < 1e-4 && (bitangent.length() - 1.0).abs() < 1e-4);
+            assert!(tangent.dot(normal).abs() < 1e-4 && bitangent.dot(normal).abs() < 1e-4);
+            assert!(tangent.dot(bitangent).abs() < 1e-4);
+
+            let uv = object.get_texture_coord(point);
+            let step = |dir: Vec3A| object.get_texture_coord(&(*point + dir * 0.001)) - uv;
+            assert!(step(tangent).x > 0.0, "{:?}", point);
+            assert!(step(bitangent).y > 0.0, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn tangents_stay_valid_at_the_poles() {
+        let sphere = Sphere::create(Vec3A::ZERO, 1.0, Material::create(Vec3A::ONE, 0.1));
+        let (tangent, bitangent) = sphere.tangents(&Vec3A::Y, Vec3A::Y);
+        assert!((tangent.length() - 1.0).abs() < 1e-4 && (bitangent.length() - 1.0).abs() < 1e-4);
+        assert!(tangent.dot(Vec3A::Y).abs() < 1e-4 && bitangent.dot(Vec3A::Y).abs() < 1e-4);
+    }
+
+    #[test]
+    fn mesh_tangents_are_smooth_across_edges() {
+        let dir = std::env::temp_dir().join("rust_tracer_mesh_tangents_are_smooth_across_edges");
+        fs::create_dir_all(&dir).unwrap();
+        // a bent quad, the two triangles map the texture differently
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0.5\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 -0.5 -1\n\
+                   f 1/1/1 2/2/1 3/3/1\nf 1/1/1 3/3/1 4/4/1\n";
+        fs::write(dir.join("bent.obj"), obj).unwrap();
+        let mut textures = TextureCache::create(0);
+        let triangles = load_obj(dir.join("bent.obj"), &mut textures, &Affine3A::IDENTITY, Material::create(Vec3A::ONE, 0.0)).unwrap();
+        assert_eq!(triangles.len(), 2);
+
+        // both triangles give the same frame on the shared edge
+        let point = Vec3A::new(0.5, 0.5, 0.25);
+        let normal = Vec3A::new(0.0, -0.5, -1.0).normalize();
+        let (t0, b0) = triangles[0].tangents(&point, normal);
+        let (t1, b1) = triangles[1].tangents(&point, normal);
+        assert!((t0 - t1).length() < 1e-4 && (b0 - b1).length() < 1e-4, "{:?} {:?}", t0, t1);
+        // the tangents of the faces alone would differ
+        let face = |triangle: &Triangle| {
+            let (du, dv) = triangle.uv_derivatives();
+            tangent_frame(normal, du, dv).0
+        };
+        assert!((face(&triangles[0]) - face(&triangles[1])).length() > 0.01);
+    }
+}
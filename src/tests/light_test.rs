@@ -0,0 +1,246 @@
+#[cfg(test)]
+mod light_test {
+    use std::path::Path;
+    use std::f32::consts::PI;
+    use glam::{Quat, Vec2, Vec3A};
+    use image::{Rgb, Rgb32FImage};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::frame_buffer::create_frame_buffer;
+    use crate::geometry::ray::Ray;
+    use crate::scene::area_light::{AreaLight, LightShape};
+    use crate::scene::environment::EnvironmentMap;
+    use crate::scene::light::{LightSource, SpotLight};
+    use crate::scene::scene::{Integrator, Scene};
+
+    const SCENE: &str = r#"
+        [materials.white]
+        color = [0.5, 0.5, 0.5]
+
+        [[planes]]
+        point = [0.0, 0.0, 0.0]
+        normal = [0.0, 1.0, 0.0]
+        material = "white"
+
+        [[area_lights]]
+        color = [4.0, 4.0, 4.0]
+        samples = 256
+        shape = { type = "disk", center = [0.0, 2.0, 10.0], normal = [0.0, -1.0, 0.0], radius = 1.0 }
+    "#;
+
+    #[test]
+    fn disk_light_irradiance_matches_the_analytic_value() {
+        let mut scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+        scene.width = 16;
+        scene.height = 16;
+        scene.integrator = Integrator::PathTracer;
+        scene.camera.look_at(Vec3A::new(0.0, 0.0, 10.0), Vec3A::Y);
+
+        let mut frame = create_frame_buffer(scene.width, scene.height);
+        scene.render_pass(&mut frame);
+        // irradiance below the center of a disk with radius r at height h: L * pi * r² / (r² + h²)
+        let irradiance = 4.0 * std::f32::consts::PI / 5.0;
+        let expected = 0.5 * irradiance / std::f32::consts::PI;
+        let color = frame.get_pixel(8, 8);
+        assert!((color.x - expected).abs() < 0.01 * expected, "{} != {}", color.x, expected);
+    }
+
+    #[test]
+    fn area_lights_are_visible_to_camera_rays() {
+        for integrator in [Integrator::Whitted, Integrator::PathTracer] {
+            let mut scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+            scene.width = 16;
+            scene.height = 16;
+            scene.integrator = integrator;
+            // close to the light, the near plane must not cut it off
+            scene.camera.org = Vec3A::new(0.0, 1.0, 9.0);
+            scene.camera.screen_dist = 0.1;
+            scene.camera.look_at(Vec3A::new(0.0, 2.0, 10.0), Vec3A::Z);
+
+            let mut frame = create_frame_buffer(scene.width, scene.height);
+            scene.render_pass(&mut frame);
+            assert_eq!(frame.get_pixel(8, 8), Vec3A::splat(4.0), "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn light_samples_lie_on_the_light() {
+        let shapes = [
+            LightShape::Sphere { center: Vec3A::new(1.0, 5.0, 2.0), radius: 0.5 },
+            LightShape::Rect { center: Vec3A::new(0.0, 5.0, 0.0), u: Vec3A::new(2.0, 0.0, 0.0), v: Vec3A::new(0.0, 0.0, 1.0) },
+            LightShape::Disk { center: Vec3A::new(0.0, 5.0, 0.0), normal: -Vec3A::Y, radius: 1.5 },
+        ];
+        let mut rng = StdRng::seed_from_u64(11);
+        for shape in shapes {
+            let light = AreaLight::create(shape, Vec3A::ONE, 1);
+            let point = Vec3A::new(0.3, 0.0, -0.2);
+            for _ in 0..100 {
+                let sample = light.sample(point, Vec2::new(rng.gen(), rng.gen())).expect("the light faces the point");
+                assert!(sample.intensity.min_element() > 0.0);
+                let ray = Ray { org: point, dir: sample.dir };
+                let t = light.intersect(&ray).expect("the ray towards a sample hits the light");
+                assert!((t - sample.dist).abs() < 1e-3, "{:?}", shape);
+            }
+            // one sided lights are dark from behind
+            if !matches!(shape, LightShape::Sphere { .. }) {
+                assert!(light.sample(Vec3A::new(0.0, 10.0, 0.0), Vec2::splat(0.5)).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn sun_light_is_the_same_everywhere_and_casts_parallel_shadows() {
+        // a sun straight from above, the box shadows exactly the square below it
+        let source = r#"
+            [materials.white]
+            color = [0.5, 0.5, 0.5]
+
+            # black, so no light bounces back from the box
+            [materials.black]
+            color = [0.0, 0.0, 0.0]
+
+            [[planes]]
+            point = [0.0, 0.0, 0.0]
+            normal = [0.0, 1.0, 0.0]
+            material = "white"
+
+            [[boxes]]
+            min = [-1.0, 50.0, 9.0]
+            max = [1.0, 51.0, 11.0]
+            material = "black"
+
+            [[lights]]
+            type = "directional"
+            dir = [0.0, -1.0, 0.0]
+            color = [2.0, 2.0, 2.0]
+        "#;
+        let mut scene = Scene::from_toml(source, Path::new("")).unwrap();
+        scene.integrator = Integrator::PathTracer;
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut irradiance = |x: f32, z: f32| {
+            let ray = Ray { org: Vec3A::new(x, 1.0, z), dir: -Vec3A::Y };
+            scene.trace(&ray, &mut rng).x
+        };
+        let lit = 0.5 * 2.0 / std::f32::consts::PI;
+        for (x, z) in [(3.0, 10.0), (-20.0, 100.0), (0.0, 12.0)] {
+            assert!((irradiance(x, z) - lit).abs() < 1e-5);
+        }
+        assert_eq!(irradiance(0.0, 10.0), 0.0);
+        assert_eq!(irradiance(0.9, 9.1), 0.0);
+    }
+
+    #[test]
+    fn spot_light_fades_between_the_cones() {
+        let spot = SpotLight {
+            org: Vec3A::new(0.0, 10.0, 0.0),
+            dir: -Vec3A::Y,
+            inner_angle: 20f32.to_radians(),
+            outer_angle: 40f32.to_radians(),
+            color: Vec3A::splat(100.0),
+        };
+        let at_angle = |degrees: f32| Vec3A::new(10.0 * degrees.to_radians().tan(), 0.0, 0.0);
+
+        let center = spot.sample(Vec3A::ZERO, Vec2::ZERO).unwrap();
+        assert_eq!(center.dir, Vec3A::Y);
+        assert_eq!(center.dist, 10.0);
+        // inverse square falloff
+        assert_eq!(center.intensity, Vec3A::splat(1.0));
+
+        let inner = spot.sample(at_angle(19.0), Vec2::ZERO).unwrap();
+        assert!((inner.intensity.x * inner.dist * inner.dist - 100.0).abs() < 1e-3);
+        let between = spot.sample(at_angle(30.0), Vec2::ZERO).unwrap();
+        let factor = between.intensity.x * between.dist * between.dist / 100.0;
+        assert!(factor > 0.3 && factor < 0.7, "{}", factor);
+        assert!(spot.sample(at_angle(41.0), Vec2::ZERO).is_none());
+    }
+
+    #[test]
+    fn emissive_surfaces_glow_without_lights_and_show_up_in_mirrors() {
+        let source = r#"
+            [materials.glow]
+            color = [0.0, 0.0, 0.0]
+            emissive = [1.0, 0.5, 0.25]
+            emissive_intensity = 2.0
+
+            [materials.mirror]
+            reflect = 1.0
+
+            [[spheres]]
+            center = [0.0, 0.0, 10.0]
+            radius = 1.0
+            material = "glow"
+
+            [[planes]]
+            point = [0.0, 0.0, 20.0]
+            normal = [0.0, 0.0, -1.0]
+            material = "mirror"
+        "#;
+        let emitted = Vec3A::new(2.0, 1.0, 0.5);
+        for integrator in [Integrator::Whitted, Integrator::PathTracer] {
+            let mut scene = Scene::from_toml(source, Path::new("")).unwrap();
+            scene.integrator = integrator;
+            let mut rng = StdRng::seed_from_u64(5);
+            let direct = Ray { org: Vec3A::ZERO, dir: Vec3A::Z };
+            assert_eq!(scene.trace(&direct, &mut rng), emitted, "{:?}", integrator);
+            // the back of the sphere, seen in the mirror behind it
+            let reflected = Ray { org: Vec3A::new(0.0, 0.0, 15.0), dir: Vec3A::Z };
+            assert_eq!(scene.trace(&reflected, &mut rng), emitted, "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn uniform_environment_lights_a_diffuse_plane() {
+        let source = r#"
+            [materials.white]
+            color = [0.5, 0.5, 0.5]
+
+            [[planes]]
+            point = [0.0, 0.0, 0.0]
+            normal = [0.0, 1.0, 0.0]
+            material = "white"
+        "#;
+        let mut scene = Scene::from_toml(source, Path::new("")).unwrap();
+        scene.integrator = Integrator::PathTracer;
+        scene.set_environment(EnvironmentMap::create(Rgb32FImage::from_pixel(16, 8, Rgb([2.0, 2.0, 2.0])), 0.0, 1.0, 64));
+        let mut rng = StdRng::seed_from_u64(3);
+        let ray = Ray { org: Vec3A::new(0.0, 5.0, 0.0), dir: -Vec3A::Y };
+        let count = 200;
+        let average = (0..count).map(|_| scene.trace(&ray, &mut rng).x).sum::<f32>() / count as f32;
+        // irradiance pi * L from the upper hemisphere, reflected as albedo * E / pi
+        assert!((average - 1.0).abs() < 0.02, "{}", average);
+        // the sky itself is seen at its radiance
+        assert_eq!(scene.trace(&Ray { org: ray.org, dir: Vec3A::Y }, &mut rng), Vec3A::splat(2.0));
+    }
+
+    #[test]
+    fn environment_samples_find_the_bright_spot_and_follow_the_rotation() {
+        let mut image = Rgb32FImage::from_pixel(64, 32, Rgb([0.1, 0.1, 0.1]));
+        // a little above the horizon, in the direction of +z
+        image.put_pixel(32, 12, Rgb([1000.0, 1000.0, 1000.0]));
+        let (theta, phi) = (12.5 / 32.0 * PI, 0.5 / 64.0 * 2.0 * PI);
+        let mut rng = StdRng::seed_from_u64(9);
+        for rotation in [0.0f32, 90.0] {
+            let environment = EnvironmentMap::create(image.clone(), rotation.to_radians(), 1.0, 1);
+            let sun = Quat::from_rotation_y(rotation.to_radians())
+                * Vec3A::new(theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos());
+            assert!((environment.radiance(sun).x - 1000.0).abs() < 1.0, "{}", rotation);
+
+            // each sample estimates the light from the whole sphere
+            let count = 20000;
+            let mut total = 0.0;
+            let mut towards_sun = 0;
+            for _ in 0..count {
+                let sample = environment.sample(Vec3A::ZERO, Vec2::new(rng.gen(), rng.gen())).unwrap();
+                total += sample.intensity.x;
+                if sample.dir.dot(sun) > 0.95 {
+                    towards_sun += 1;
+                }
+            }
+            assert!(towards_sun > count * 9 / 10, "{} {}", rotation, towards_sun);
+            // integral over the sphere, the bright pixel covers about 2pi/64 * pi/32 * sin(theta)
+            let expected = 0.1 * 4.0 * PI + 999.9 * 2.0 * PI / 64.0 * PI / 32.0 * theta.sin();
+            let average = total / count as f32;
+            assert!((average - expected).abs() < 0.05 * expected, "{} != {}", average, expected);
+        }
+    }
+}
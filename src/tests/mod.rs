@@ -1 +1,10 @@
-pub mod geometry_test;
\ No newline at end of file
+pub mod brdf_test;
+pub mod camera_test;
+pub mod color_test;
+pub mod geometry_test;
+pub mod light_test;
+pub mod procedural_test;
+pub mod render_test;
+pub mod sampling_test;
+pub mod scene_file_test;
+pub mod texture_test;
\ No newline at end of file
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod procedural_test {
+    use std::sync::Arc;
+    use glam::{Vec2, Vec3A};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::geometry::plane::Plane;
+    use crate::geometry::ray::{Ray, RayCone};
+    use crate::geometry::sphere::Sphere;
+    use crate::geometry::traceable::Traceable;
+    use crate::scene::material::Material;
+    use crate::scene::procedural::{fbm, perlin, turbulence, voronoi, Pattern, Procedural, TextureSpace};
+    use crate::scene::scene::Scene;
+    use crate::scene::texture::Texture;
+
+    #[test]
+    fn noise_is_smooth_and_stays_in_range() {
+        for i in 0..200 {
+            let p = Vec3A::new(i as f32 * 0.37, i as f32 * 0.11 - 7.0, i as f32 * -0.23);
+            assert_eq!(perlin(p.floor()), 0.0);
+            assert!(perlin(p).abs() <= 1.1, "{} at {:?}", perlin(p), p);
+            assert!((perlin(p + Vec3A::splat(0.001)) - perlin(p)).abs() < 0.01, "{:?}", p);
+            assert!(fbm(p, 6).abs() <= 1.1);
+            assert!((0.0..=1.0).contains(&turbulence(p, 6)));
+            assert!((0.0..=1.0).contains(&voronoi(p)));
+        }
+        // not flat
+        let values: Vec<f32> = (0..100).map(|i| perlin(Vec3A::new(i as f32 * 0.5 + 0.25, 0.3, 0.7))).collect();
+        assert!(values.iter().any(|v| *v > 0.2) && values.iter().any(|v| *v < -0.2));
+    }
+
+    #[test]
+    fn checker_alternates_in_object_and_uv_space() {
+        let mut checker = Procedural::create(Pattern::Checker, 2.0);
+        let at = |checker: &Procedural, uv: Vec2, p: Vec3A| checker.color(uv, p).x;
+        assert_eq!(at(&checker, Vec2::ZERO, Vec3A::new(0.1, 0.1, 0.1)), 0.0);
+        assert_eq!(at(&checker, Vec2::ZERO, Vec3A::new(0.6, 0.1, 0.1)), 1.0);
+        assert_eq!(at(&checker, Vec2::ZERO, Vec3A::new(0.6, -0.1, 0.1)), 0.0);
+
+        checker.space = TextureSpace::Uv;
+        assert_eq!(at(&checker, Vec2::new(0.1, 0.1), Vec3A::new(0.6, 0.1, 0.1)), 0.0);
+        assert_eq!(at(&checker, Vec2::new(0.6, 0.1), Vec3A::new(0.1, 0.1, 0.1)), 1.0);
+    }
+
+    #[test]
+    fn checker_cells_of_a_plane_have_one_color() {
+        let plane = Plane::create(Vec3A::ZERO, Vec3A::Y, 1.0, Material::create(Vec3A::ONE, 0.0));
+        let checker = Procedural::create(Pattern::Checker, 2.0);
+        let mut rng = StdRng::seed_from_u64(7);
+        // points inside a single cell, hit from all sides so the height of the hits scatters around 0
+        let colors: Vec<f32> = (0..10000)
+            .map(|_| {
+                let target = Vec3A::new(rng.gen_range(0.05..0.45), 0.0, rng.gen_range(0.05..0.45));
+                let org = Vec3A::new(rng.gen_range(-10.0..10.0), rng.gen_range(1.0..10.0), rng.gen_range(-10.0..10.0));
+                let ray = Ray {
+                    org,
+                    dir: (target - org).normalize(),
+                };
+                let mut t = 0.0;
+                assert!(plane.intersect(&ray, &mut t));
+                let point = ray.point_at(t);
+                checker.color(plane.get_texture_coord(&point), plane.get_object_coord(&point)).x
+            })
+            .collect();
+        let flipped = colors.iter().filter(|c| **c != colors[0]).count();
+        assert_eq!(flipped, 0);
+    }
+
+    #[test]
+    fn object_space_textures_follow_the_object() {
+        let scene = Scene::create_empty(8, 8);
+        let material = Arc::new(Material {
+            texture: Some(Box::new(Texture::procedural(Procedural::create(Pattern::Marble, 3.0)))),
+            ..Default::default()
+        });
+        let ray = Ray {
+            org: Vec3A::new(0.3, 0.2, -10.0),
+            dir: Vec3A::Z,
+        };
+        let colors: Vec<Vec3A> = [Vec3A::ZERO, Vec3A::new(5.0, -2.0, 1.0)]
+            .iter()
+            .map(|center| {
+                let sphere = Sphere::create(*center, 1.0, material.clone());
+                let ray = Ray { org: ray.org + *center, dir: ray.dir };
+                let mut t = 0.0;
+                assert!(sphere.intersect(&ray, &mut t));
+                scene.surface_hit(&ray, RayCone::default(), t, &sphere).color
+            })
+            .collect();
+        assert!((colors[0] - colors[1]).length() < 1e-4, "{:?}", colors);
+    }
+
+    #[test]
+    fn procedural_normal_maps_tilt_the_normal_downhill() {
+        let scene = Scene::create_empty(8, 8);
+        let noise = Procedural::create(Pattern::Noise, 1.0);
+        let material = Arc::new(Material {
+            normal_map: Some(Box::new(Texture::procedural(noise))),
+            bump_depth: 0.5,
+            ..Default::default()
+        });
+        let plane = Plane::create(Vec3A::ZERO, Vec3A::Y, 1.0, material);
+        for x in 0..8 {
+            let ray = Ray {
+                org: Vec3A::new(x as f32 * 0.3 + 0.1, 5.0, 0.4),
+                dir: -Vec3A::Y,
+            };
+            let hit = scene.surface_hit(&ray, RayCone::default(), 5.0, &plane);
+            assert!(hit.normal.dot(Vec3A::Y) > 0.5);
+            // the surface gets lower in the direction the normal leans to
+            let downhill = (hit.normal - Vec3A::Y * hit.normal.y).normalize() * 0.01;
+            assert!(noise.value(hit.point + downhill) < noise.value(hit.point), "{:?}", hit.point);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(test)]
+mod render_test {
+    use std::f32::consts::PI;
+    use std::path::Path;
+    use glam::{Vec2, Vec3A};
+    use crate::frame_buffer::create_frame_buffer;
+    use crate::scene::camera::Projection;
+    use crate::scene::scene::{Integrator, Scene};
+
+    const SCENE: &str = r#"
+        [materials.mirror]
+        color = [0.3, 0.3, 0.3]
+        reflect = 0.9
+
+        [materials.green]
+        color = [0.2, 1.0, 0.1]
+        reflect = 0.3
+
+        [[spheres]]
+        center = [2.0, 5.0, 10.0]
+        radius = 2.0
+        material = "mirror"
+
+        [[spheres]]
+        center = [-2.0, 4.0, 12.0]
+        radius = 2.5
+        material = "green"
+
+        [[lights]]
+        type = "point"
+        org = [0.0, 10.0, -10.0]
+        intensity = 500.0
+    "#;
+
+    fn render(scene: &Scene) -> Vec<u8> {
+        let mut screen = vec![0u8; (scene.width * scene.height * 4) as usize];
+        scene.render(&mut screen);
+        screen
+    }
+
+    #[test]
+    fn parallel_render_matches_serial_render() {
+        let mut scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+        scene.width = 64;
+        scene.height = 48;
+
+        let serial_pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let serial = serial_pool.install(|| render(&scene));
+        let parallel = render(&scene);
+
+        assert!(serial.iter().any(|c| *c != 0));
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn progressive_passes_are_reproducible() {
+        let mut scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+        scene.width = 32;
+        scene.height = 24;
+        scene.integrator = Integrator::PathTracer;
+
+        let accumulate = || {
+            let mut frame = create_frame_buffer(scene.width, scene.height);
+            for _ in 0..4 {
+                scene.render_pass(&mut frame);
+            }
+            assert_eq!(frame.samples, 4);
+            (0..scene.height)
+                .flat_map(|y| (0..scene.width).map(move |x| (x, y)))
+                .map(|(x, y)| frame.get_pixel(x, y))
+                .collect::<Vec<_>>()
+        };
+        let serial_pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        assert_eq!(serial_pool.install(accumulate), accumulate());
+    }
+
+    #[test]
+    fn path_tracer_matches_direct_light_on_a_diffuse_plane() {
+        // without sky and other objects, all light on the plane comes straight from the lamp
+        let scene_source = r#"
+            [materials.white]
+            color = [0.5, 0.5, 0.5]
+
+            [[planes]]
+            point = [0.0, 0.0, 0.0]
+            normal = [0.0, 1.0, 0.0]
+            material = "white"
+
+            [[lights]]
+            type = "point"
+            org = [0.0, 10.0, 10.0]
+            intensity = 300.0
+        "#;
+        let mut scene = Scene::from_toml(scene_source, Path::new("")).unwrap();
+        scene.width = 16;
+        scene.height = 16;
+        scene.integrator = Integrator::PathTracer;
+
+        let mut frame = create_frame_buffer(scene.width, scene.height);
+        scene.render_pass(&mut frame);
+
+        let row = 12;
+        for x in 0..scene.width {
+            let ray = scene.camera.primary_ray(scene.width, scene.height, x as f32, row as f32, Vec2::ZERO);
+            let point = ray.org + ray.dir * (-ray.org.y / ray.dir.y);
+            let to_light = Vec3A::new(0.0, 10.0, 10.0) - point;
+            // inverse square falloff
+            let expected = 0.5 * 300.0 * to_light.normalize().y / to_light.length_squared() / PI;
+            let color = frame.get_pixel(x, row);
+            assert!((color.x - expected).abs() < 1e-4, "pixel {}: {} != {}", x, color.x, expected);
+        }
+    }
+
+    #[test]
+    fn supersampling_smooths_edges() {
+        // a flat face in the middle of the view, its vertical edges lie between two pixel columns
+        let scene_source = r#"
+            [materials.white]
+            color = [1.0, 1.0, 1.0]
+
+            [[boxes]]
+            min = [0.37, 0.0, 20.0]
+            max = [5.0, 10.0, 21.0]
+            material = "white"
+        "#;
+        let mut scene = Scene::from_toml(scene_source, Path::new("")).unwrap();
+        scene.width = 32;
+        scene.height = 8;
+
+        let mut row_of = |samples_per_pixel| {
+            scene.samples_per_pixel = samples_per_pixel;
+            let mut frame = create_frame_buffer(scene.width, scene.height);
+            scene.render_pass(&mut frame);
+            (0..scene.width).map(|x| frame.get_pixel(x, 4).x).collect::<Vec<_>>()
+        };
+        let blended = |row: &[f32]| {
+            let max = row.iter().cloned().fold(0.0, f32::max);
+            row.iter().filter(|c| **c > 0.1 * max && **c < 0.9 * max).count()
+        };
+        assert_eq!(blended(&row_of(1)), 0);
+        assert!(blended(&row_of(16)) > 0);
+    }
+
+    #[test]
+    fn thin_lens_rays_meet_on_the_focus_plane() {
+        let mut scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+        scene.width = 64;
+        scene.height = 48;
+        scene.camera.aperture = 0.5;
+        let point = scene.pick(32.0, 24.0).expect("the green sphere is in the middle of the view");
+        scene.camera.focus_on(point);
+
+        let camera = &scene.camera;
+        for lens in [Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(-0.3, 0.7)] {
+            let ray = camera.primary_ray(scene.width, scene.height, 32.0, 24.0, lens);
+            // distance along the ray to the focus plane
+            let t = (camera.focus_dist - (ray.org - camera.org).dot(camera.dir())) / ray.dir.dot(camera.dir());
+            assert!((ray.point_at(t) - point).length() < 1e-3, "lens {:?} misses the focus point", lens);
+        }
+        // the lens only changes the rays if the aperture is open
+        scene.camera.aperture = 0.0;
+        let pinhole = scene.camera.primary_ray(scene.width, scene.height, 32.0, 24.0, Vec2::new(1.0, 0.0));
+        assert!((pinhole.point_at(0.0) - scene.camera.primary_ray(scene.width, scene.height, 32.0, 24.0, Vec2::ZERO).org).length() < 1e-6);
+    }
+
+    #[test]
+    fn projections_map_pixels_to_directions() {
+        let mut scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+        let camera = &mut scene.camera;
+        let (width, height) = (200, 100);
+        let ray = |camera: &crate::scene::camera::Camera, x: f32, y: f32| camera.primary_ray(width, height, x, y, Vec2::ZERO);
+
+        camera.projection = Projection::Orthographic { height: 10.0 };
+        let (a, b) = (ray(camera, 10.0, 10.0), ray(camera, 110.0, 10.0));
+        assert_eq!(a.dir, camera.dir());
+        assert_eq!(b.dir, camera.dir());
+        // 100 pixels are half the view height
+        assert!(((a.org - b.org).length() - 10.0).abs() < 1e-4);
+
+        camera.projection = Projection::Fisheye { fov: 180.0 };
+        assert!(ray(camera, 100.0, 50.0).dir.abs_diff_eq(camera.dir(), 1e-6));
+        // the top edge of a 180° fisheye looks straight up
+        assert!(ray(camera, 100.0, 0.0).dir.abs_diff_eq(Vec3A::Y, 1e-5));
+
+        camera.projection = Projection::Equirectangular;
+        assert!(ray(camera, 100.0, 50.0).dir.abs_diff_eq(camera.dir(), 1e-6));
+        assert!(ray(camera, 0.0, 50.0).dir.abs_diff_eq(-camera.dir(), 1e-5));
+        assert!(ray(camera, 100.0, 0.0).dir.abs_diff_eq(Vec3A::Y, 1e-5));
+        // a quarter turn to the right, screen right is -x for a camera looking along +z
+        assert!(ray(camera, 150.0, 50.0).dir.abs_diff_eq(-Vec3A::X, 1e-5));
+    }
+}
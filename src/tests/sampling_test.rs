@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod sampling_test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use glam::Vec2;
+    use crate::scene::camera::Bokeh;
+    use crate::scene::filter::Filter;
+    use crate::scene::sampling::stratified_sample;
+
+    #[test]
+    fn filters_peak_at_the_pixel_center() {
+        for filter in [Filter::Box, Filter::Tent, Filter::Gaussian, Filter::Mitchell] {
+            let center = filter.weight(0.0, 0.0);
+            assert!(center > 0.0);
+            assert!(filter.weight(0.3, 0.1) <= center, "{:?}", filter);
+            assert_eq!(filter.weight(filter.radius() + 0.01, 0.0), 0.0, "{:?}", filter);
+        }
+        assert_eq!(Filter::Box.weight(0.49, -0.49), 1.0);
+        assert_eq!(Filter::Tent.weight(0.5, 0.0), 0.5);
+        // the negative lobe sharpens edges
+        assert!(Filter::Mitchell.weight(1.5, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn stratified_samples_fill_their_own_cells() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..10 {
+            let mut cells: Vec<(u32, u32)> = (0..16)
+                .map(|i| stratified_sample(i, 16, &mut rng))
+                .map(|p| ((p.x * 4.0) as u32, (p.y * 4.0) as u32))
+                .collect();
+            cells.sort_unstable();
+            cells.dedup();
+            assert_eq!(cells.len(), 16);
+        }
+        // counts that are no square still stay inside the unit square
+        for i in 0..7 {
+            let p = stratified_sample(i, 7, &mut rng);
+            assert!((0.0..1.0).contains(&p.x) && (0.0..1.0).contains(&p.y));
+        }
+    }
+
+    #[test]
+    fn bokeh_samples_stay_on_the_lens() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let hexagon = Bokeh::Polygon { blades: 6 };
+        // the inner radius of a hexagon with corners on the unit circle
+        let inner_radius = (std::f32::consts::PI / 6.0).cos();
+        let mut outside_inner_circle = 0;
+        for i in 0..1000 {
+            let u = stratified_sample(i, 1000, &mut rng);
+            assert!(Bokeh::Disk.sample(u).length() <= 1.0 + 1e-5);
+            let p = hexagon.sample(u);
+            assert!(p.length() <= 1.0 + 1e-5);
+            if p.length() > inner_radius {
+                outside_inner_circle += 1;
+            }
+        }
+        // the corners get samples too
+        assert!(outside_inner_circle > 0);
+        assert_eq!(Bokeh::Disk.sample(Vec2::splat(0.5)), Vec2::ZERO);
+    }
+}
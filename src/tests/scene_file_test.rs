@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod scene_file_test {
+    use std::fs;
+    use std::path::Path;
+    use glam::Vec3A;
+    use crate::scene::scene::Scene;
+    use crate::scene::scene_file::SceneLoadError;
+
+    #[test]
+    fn scene_file_is_parsed() {
+        let source = r#"
+            max_depth = 2
+
+            [camera]
+            org = [1.0, 2.0, 3.0]
+            dir = [0.0, 0.0, 2.0]
+
+            [materials.green]
+            color = [0.2, 1.0, 0.1]
+
+            [[spheres]]
+            center = [0.0, 0.0, 10.0]
+            radius = 1.0
+            material = "green"
+
+            [[lights]]
+            type = "point"
+            org = [0.0, 10.0, 0.0]
+        "#;
+        let scene = Scene::from_toml(source, Path::new("")).unwrap();
+        assert_eq!(scene.max_depth, 2);
+        assert_eq!(scene.camera.org, Vec3A::new(1.0, 2.0, 3.0));
+        assert_eq!(scene.camera.dir(), Vec3A::Z);
+    }
+
+    #[test]
+    fn unknown_material_is_an_error() {
+        let source = r#"
+            [[spheres]]
+            center = [0.0, 0.0, 10.0]
+            radius = 1.0
+            material = "missing"
+        "#;
+        let result = Scene::from_toml(source, Path::new(""));
+        assert!(matches!(result, Err(SceneLoadError::UnknownMaterial { .. })));
+    }
+
+    #[test]
+    fn missing_texture_is_an_error() {
+        let source = r#"
+            sky = "does/not/exist.png"
+        "#;
+        let result = Scene::from_toml(source, Path::new(""));
+        assert!(matches!(result, Err(SceneLoadError::Texture { .. })));
+    }
+
+    #[test]
+    fn incomplete_cube_map_is_an_error() {
+        let invalid = |source: &str| match Scene::from_toml(source, Path::new("")) {
+            Err(SceneLoadError::Invalid { reason }) => reason,
+            _ => panic!("expected an invalid scene: {}", source),
+        };
+        let reason = invalid("[cube_map]\ncross = \"cross.png\"\ntop = \"top.png\"\n");
+        assert!(reason.contains("`cross` and `top`"), "{}", reason);
+        let reason = invalid("[cube_map]\nright = \"r.png\"\nleft = \"l.png\"\ntop = \"t.png\"\nbottom = \"b.png\"\n");
+        assert!(reason.contains("missing the faces `front`, `back`"), "{}", reason);
+        let reason = invalid("sky = \"sky.png\"\n[environment]\npath = \"studio.hdr\"\n");
+        assert!(reason.contains("`sky` and `environment`"), "{}", reason);
+    }
+
+    #[test]
+    fn cube_map_faces_are_loaded_once() {
+        let dir = std::env::temp_dir().join("rust_tracer_cube_map_faces_are_loaded_once");
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(4, 4).save(dir.join("face.png")).unwrap();
+        let source = r#"
+            [cube_map]
+            right = "face.png"
+            left = "face.png"
+            top = "face.png"
+            bottom = "./face.png"
+            front = "face.png"
+            back = "face.png"
+        "#;
+        let scene = Scene::from_toml(source, &dir).unwrap();
+        assert_eq!(scene.texture_count(), 1);
+        assert_eq!(scene.texture_memory(), 4 * 4 * 3);
+    }
+
+    #[test]
+    fn obj_mesh_is_loaded() {
+        let dir = std::env::temp_dir().join("rust_tracer_obj_mesh_is_loaded");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("quad.mtl"), "newmtl red\nKd 1.0 0.0 0.0\n").unwrap();
+        fs::write(dir.join("quad.obj"), "mtllib quad.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nusemtl red\nf 1 2 3 4\n").unwrap();
+        fs::write(dir.join("scene.toml"), "[[meshes]]\npath = \"quad.obj\"\nposition = [0.0, 0.0, 10.0]\n").unwrap();
+
+        assert!(Scene::load(dir.join("scene.toml")).is_ok());
+
+        fs::write(dir.join("scene.toml"), "[[meshes]]\npath = \"missing.obj\"\n").unwrap();
+        let result = Scene::load(dir.join("scene.toml"));
+        assert!(matches!(result, Err(SceneLoadError::Mesh { .. })));
+    }
+
+    #[test]
+    fn texture_settings_are_loaded() {
+        let dir = std::env::temp_dir().join("rust_tracer_texture_settings_are_loaded");
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(2, 2).save(dir.join("tiles.png")).unwrap();
+        let source = r#"
+            [materials.tiles]
+            texture = { path = "tiles.png", wrap = "mirror", filter = "nearest", scale = [4.0, 4.0], rotation = 45.0 }
+            normal_map = "tiles.png"
+
+            [materials.more_tiles]
+            texture = "./tiles.png"
+        "#;
+        let scene = Scene::from_toml(source, &dir).unwrap();
+        // one color and one data texture of the file, shared by both materials
+        assert_eq!(scene.textures.len(), 2);
+
+        let source = r#"
+            [materials.tiles]
+            texture = { path = "tiles.png", wrap = "sideways" }
+        "#;
+        assert!(matches!(Scene::from_toml(source, &dir), Err(SceneLoadError::Parse { .. })));
+
+        // misspelt keys and values are named
+        let error = |texture: &str| Scene::from_toml(&format!("[materials.tiles]\ntexture = {}\n", texture), &dir).err().unwrap().to_string();
+        let message = error(r#"{ path = "tiles.png", wrpa = "clamp" }"#);
+        assert!(message.contains("unknown field `wrpa`"), "{}", message);
+        let message = error(r#"{ pattern = "marbel" }"#);
+        assert!(message.contains("unknown variant `marbel`"), "{}", message);
+    }
+}
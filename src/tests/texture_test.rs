@@ -0,0 +1,294 @@
+#[cfg(test)]
+mod texture_test {
+    use std::sync::Arc;
+    use glam::{Vec2, Vec3A};
+    use image::{Rgb, RgbImage};
+    use image::imageops::replace;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::geometry::plane::Plane;
+    use crate::geometry::ray::{Ray, RayCone};
+    use crate::geometry::traceable::Traceable;
+    use crate::scene::cube_map::CubeMap;
+    use crate::scene::material::{Material, NormalMapFormat};
+    use crate::scene::mip_map::{Footprint, MipMap};
+    use crate::scene::scene::Scene;
+    use crate::scene::texture::{Texture, TextureFilter, UvTransform, WrapMode};
+    use crate::scene::texture_cache::{TextureCache, TextureLoadError};
+
+    /// one color per face, in the order right, left, top, bottom, front, back
+    const COLORS: [[u8; 3]; 6] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0], [0, 255, 255], [255, 0, 255]];
+
+    fn linear(color: [u8; 3]) -> Vec3A {
+        Vec3A::new(color[0] as f32, color[1] as f32, color[2] as f32) / 255.0
+    }
+
+    fn solid_faces(size: u32) -> [Arc<RgbImage>; 6] {
+        COLORS.map(|color| Arc::new(RgbImage::from_pixel(size, size, Rgb(color))))
+    }
+
+    #[test]
+    fn cube_map_faces_are_seen_in_their_directions() {
+        let cube_map = CubeMap::create(solid_faces(4)).unwrap();
+        let directions = [-Vec3A::X, Vec3A::X, Vec3A::Y, -Vec3A::Y, Vec3A::Z, -Vec3A::Z];
+        for (dir, color) in directions.iter().zip(COLORS) {
+            assert_eq!(cube_map.color(*dir), linear(color), "{:?}", dir);
+        }
+    }
+
+    #[test]
+    fn cube_map_edges_blend_the_neighbouring_faces() {
+        let cube_map = CubeMap::create(solid_faces(8)).unwrap();
+        let (right, front, top) = (linear(COLORS[0]), linear(COLORS[4]), linear(COLORS[2]));
+        // exactly on the edge between the front and the right face
+        let edge = cube_map.color(Vec3A::new(-1.0, 0.0, 1.0));
+        assert!((edge - (right + front) * 0.5).abs().max_element() < 1e-3, "{:?}", edge);
+        // just on either side of the edge
+        let on_front = cube_map.color(Vec3A::new(-0.999, 0.0, 1.0));
+        let on_right = cube_map.color(Vec3A::new(-1.0, 0.0, 0.999));
+        assert!((on_front - on_right).abs().max_element() < 0.02, "{:?} {:?}", on_front, on_right);
+        // corners blend three faces
+        let corner = cube_map.color(Vec3A::new(-1.0, 1.0, 1.0));
+        for color in [right, front, top] {
+            assert!((corner - color).abs().max_element() < 0.9, "{:?}", corner);
+        }
+    }
+
+    #[test]
+    fn cube_map_is_cut_out_of_cross_layouts() {
+        let size = 4;
+        let faces = solid_faces(size);
+        let mut horizontal = RgbImage::new(size * 4, size * 3);
+        let mut vertical = RgbImage::new(size * 3, size * 4);
+        // cell of each face in the crosses
+        let horizontal_cells = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+        let vertical_cells = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)];
+        for (face, ((hx, hy), (vx, vy))) in faces.iter().zip(horizontal_cells.iter().zip(vertical_cells)) {
+            replace(&mut horizontal, face.as_ref(), hx * size as i64, hy * size as i64);
+            replace(&mut vertical, face.as_ref(), vx * size as i64, vy * size as i64);
+        }
+        // mark the top left corner of the back face, the vertical cross has it upside down
+        horizontal.put_pixel(3 * size, size, Rgb([0, 0, 0]));
+        vertical.put_pixel(2 * size - 1, 4 * size - 1, Rgb([0, 0, 0]));
+
+        let mut back = faces[5].as_ref().clone();
+        back.put_pixel(0, 0, Rgb([0, 0, 0]));
+        let mut expected = faces.clone();
+        expected[5] = Arc::new(back);
+        let expected = CubeMap::create(expected).unwrap();
+        for cross in [horizontal, vertical] {
+            let cube_map = CubeMap::from_cross(Arc::new(cross)).unwrap();
+            for dir in [-Vec3A::X, Vec3A::X, Vec3A::Y, -Vec3A::Y, Vec3A::Z, -Vec3A::Z, Vec3A::new(0.99, 0.99, -1.0)] {
+                assert_eq!(cube_map.color(dir), expected.color(dir), "{:?}", dir);
+            }
+        }
+        assert!(CubeMap::from_cross(Arc::new(RgbImage::new(size * 2, size))).is_err());
+    }
+
+    fn checker(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| Rgb(if (x + y) % 2 == 0 { [255; 3] } else { [0; 3] }))
+    }
+
+    #[test]
+    fn mip_maps_average_in_linear_space() {
+        let pos = Vec2::new(0.3, 0.6);
+        let wide = Footprint { dx: Vec2::new(1.0, 0.0), dy: Vec2::new(0.0, 1.0), max_anisotropy: 1 };
+        for srgb in [true, false] {
+            assert_eq!(MipMap::create(checker(8, 4), srgb).levels(), 4);
+            let texture = Texture::create(checker(8, 4), srgb);
+            let texel = if srgb { texture.get_color(&pos, &Vec3A::ZERO, &wide) } else { texture.get_pixel(&pos, &Vec3A::ZERO, &wide) };
+            assert!((texel - Vec3A::splat(0.5)).abs().max_element() < 0.01, "{:?}", texel);
+            // without a footprint the texels stay sharp
+            let sharp = texture.get_pixel(&Vec2::new(1.0 / 16.0, 1.0 / 8.0), &Vec3A::ZERO, &Footprint::default());
+            assert_eq!(sharp, Vec3A::ONE);
+        }
+    }
+
+    #[test]
+    fn anisotropic_filtering_keeps_long_footprints_sharp_across() {
+        // bands 8 texels high, white in the middle of the texture
+        let image = RgbImage::from_fn(64, 64, |_, y| Rgb(if (y / 8) % 2 == 0 { [0; 3] } else { [255; 3] }));
+        let texture = Texture::create(image, false);
+        let pos = Vec2::new(0.5, 12.0 / 64.0);
+        // 16 texels along the bands, 1 across
+        let mut footprint = Footprint { dx: Vec2::new(0.25, 0.0), dy: Vec2::new(0.0, 1.0 / 64.0), max_anisotropy: 1 };
+        let blurred = texture.get_pixel(&pos, &Vec3A::ZERO, &footprint).x;
+        assert!(blurred < 0.8, "{}", blurred);
+        footprint.max_anisotropy = 16;
+        let sharp = texture.get_pixel(&pos, &Vec3A::ZERO, &footprint).x;
+        assert!(sharp > 0.99, "{}", sharp);
+    }
+
+    #[test]
+    fn distant_textures_are_averaged_over_the_pixel() {
+        let mut scene = Scene::create_empty(64, 64);
+        let glowing_checker = Arc::new(Material {
+            color: Vec3A::ZERO,
+            emissive: Vec3A::ONE,
+            emissive_map: Some(Box::new(Texture::create(checker(256, 256), true))),
+            ..Default::default()
+        });
+        scene.add_object(Plane::create(Vec3A::ZERO, Vec3A::Y, 1.0, glowing_checker));
+        let mut rng = StdRng::seed_from_u64(1);
+        for (height, average) in [(1.0, false), (1000.0, true)] {
+            scene.camera.org = Vec3A::new(0.1, height, 0.1);
+            scene.camera.look_in(-Vec3A::Y, Vec3A::Z);
+            let ray = scene.camera.primary_ray(64, 64, 32.0, 32.0, Vec2::ZERO);
+            let color = scene.trace(&ray, &mut rng).x;
+            if average {
+                assert!((color - 0.5).abs() < 0.02, "{} at {}", color, height);
+            } else {
+                assert!(!(0.01..=0.99).contains(&color), "{} at {}", color, height);
+            }
+        }
+    }
+
+    #[test]
+    fn wrap_modes_handle_any_texture_coordinate() {
+        // a gradient from black to white along u
+        let image = RgbImage::from_fn(4, 1, |x, _| Rgb([(x * 85) as u8; 3]));
+        let sharp = Footprint::default();
+        let mut texture = Texture::create(image, false);
+        texture.filter = TextureFilter::Nearest;
+        let at = |texture: &Texture, u: f32| texture.get_pixel(&Vec2::new(u, 0.5), &Vec3A::ZERO, &sharp).x;
+        let cases = [
+            (WrapMode::Repeat, [(1.125, 0.0), (-0.125, 1.0), (-3.625, 1.0 / 3.0)]),
+            (WrapMode::Clamp, [(1.125, 1.0), (-0.125, 0.0), (-3.625, 0.0)]),
+            (WrapMode::Mirror, [(1.125, 1.0), (-0.125, 0.0), (1.875, 0.0)]),
+        ];
+        for (wrap, values) in cases {
+            texture.wrap = wrap;
+            for (u, expected) in values {
+                assert!((at(&texture, u) - expected).abs() < 1e-6, "{:?} at {}: {}", wrap, u, at(&texture, u));
+            }
+        }
+        // bilinear lookups blend across the edge the way the wrap mode continues the texture
+        texture.filter = TextureFilter::Bilinear;
+        texture.wrap = WrapMode::Repeat;
+        assert!((at(&texture, 1.0) - 0.5).abs() < 1e-6);
+        texture.wrap = WrapMode::Clamp;
+        assert!((at(&texture, 1.0) - 1.0).abs() < 1e-6);
+        assert!((at(&texture, -100.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn uv_transform_scales_rotates_and_moves_the_texture() {
+        let transform = UvTransform {
+            scale: Vec2::new(2.0, 4.0),
+            rotation: std::f32::consts::FRAC_PI_2,
+            offset: Vec2::new(0.5, 0.25),
+        };
+        // scaled to (0.2, 0.4), rotated counter-clockwise to (0.4, -0.2), then moved
+        let uv = transform.apply(Vec2::new(0.1, 0.1));
+        assert!((uv - Vec2::new(0.9, 0.05)).length() < 1e-6, "{:?}", uv);
+
+        // 4 columns of texels, only the first one white
+        let image = RgbImage::from_fn(4, 4, |x, _| Rgb(if x == 0 { [255; 3] } else { [0; 3] }));
+        let mut texture = Texture::create(image, false);
+        texture.filter = TextureFilter::Nearest;
+        texture.transform.offset = Vec2::new(-0.25, 0.0);
+        // moving the texture by -0.25 shows the first column at 0.25
+        assert_eq!(texture.get_pixel(&Vec2::new(0.3, 0.5), &Vec3A::ZERO, &Footprint::default()), Vec3A::ONE);
+        assert_eq!(texture.get_pixel(&Vec2::new(0.1, 0.5), &Vec3A::ZERO, &Footprint::default()), Vec3A::ZERO);
+    }
+
+    #[test]
+    fn plane_textures_continue_across_tiles() {
+        let plane = Plane::create(Vec3A::ZERO, Vec3A::Y, 1.0, Material::create(Vec3A::ONE, 0.0));
+        let at = |texture: &Texture, x: f32| {
+            let point = Vec3A::new(x, 0.0, -0.3);
+            texture.get_pixel(&plane.get_texture_coord(&point), &point, &Footprint::default())
+        };
+        // smooth and periodic in both directions, so repeating it has no seams
+        let wave = |i: u32| (128.0 + 120.0 * (std::f32::consts::TAU * (i as f32 + 0.5) / 32.0).sin()) as u8;
+        let mut texture = Texture::create(RgbImage::from_fn(32, 32, |x, y| Rgb([wave(x), wave(y), 0])), false);
+        texture.transform.rotation = 30f32.to_radians();
+        // the rotated texture doesn't jump where the plane's coordinates reach the next tile at x = 1
+        let (before, after) = (at(&texture, 0.999), at(&texture, 1.001));
+        assert!((before - after).length() < 0.05, "{:?} {:?}", before, after);
+
+        // a clamped texture continues its edge texels beyond the first tile
+        let mut texture = Texture::create(RgbImage::from_fn(4, 1, |x, _| Rgb([if x < 2 { 0 } else { 255 }; 3])), false);
+        texture.wrap = WrapMode::Clamp;
+        assert_eq!(at(&texture, 1.5), Vec3A::ONE);
+        assert_eq!(at(&texture, -0.5), Vec3A::ZERO);
+    }
+
+    /// shading normal where a ray straight down hits a ground plane with `material`, u grows along x and v along -z
+    fn ground_normal(material: Material) -> Vec3A {
+        let scene = Scene::create_empty(8, 8);
+        let plane = Plane::create(Vec3A::ZERO, Vec3A::Y, 1.0, Arc::new(material));
+        let ray = Ray {
+            org: Vec3A::new(0.3, 5.0, -0.4),
+            dir: -Vec3A::Y,
+        };
+        scene.surface_hit(&ray, RayCone::default(), 5.0, &plane).normal
+    }
+
+    #[test]
+    fn normal_map_green_channel_follows_the_format() {
+        // tilted towards +u and towards the top of the image in OpenGL maps
+        let tilted = || Some(Box::new(Texture::create(RgbImage::from_pixel(4, 4, Rgb([200, 200, 255])), false)));
+        let opengl = ground_normal(Material {
+            normal_map: tilted(),
+            ..Default::default()
+        });
+        let directx = ground_normal(Material {
+            normal_map: tilted(),
+            normal_map_format: NormalMapFormat::DirectX,
+            ..Default::default()
+        });
+        // the top of the image is at -v, which is +z on the plane
+        assert!(opengl.x > 0.2 && opengl.z > 0.2, "{:?}", opengl);
+        assert!(directx.x > 0.2 && directx.z < -0.2, "{:?}", directx);
+        assert!((opengl.length() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bump_maps_tilt_the_normal_downhill() {
+        // a ramp rising along u, 1 unit high over the texture, which is 1 unit wide
+        let ramp = RgbImage::from_fn(256, 4, |x, _| Rgb([x as u8; 3]));
+        let normal = ground_normal(Material {
+            bump_map: Some(Box::new(Texture::create(ramp, false))),
+            bump_depth: 1.0,
+            ..Default::default()
+        });
+        // slope 1 tilts the normal by 45 degrees
+        let expected = Vec3A::new(-1.0, 1.0, 0.0).normalize();
+        assert!((normal - expected).length() < 0.02, "{:?}", normal);
+    }
+
+    #[test]
+    fn texture_cache_loads_every_file_once() {
+        let dir = std::env::temp_dir().join("rust_tracer_texture_cache_loads_every_file_once");
+        std::fs::create_dir_all(&dir).unwrap();
+        checker(8, 4).save(dir.join("checker.png")).unwrap();
+
+        let mut cache = TextureCache::create(0);
+        let first = cache.load(dir.join("checker.png"), true).unwrap();
+        let again = cache.load(dir.join(".").join("checker.png"), true).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        // data textures are not averaged like colors, they get their own mip map
+        let data = cache.load(dir.join("checker.png"), false).unwrap();
+        assert!(!Arc::ptr_eq(&first, &data));
+        assert_eq!(cache.len(), 2);
+        // 8x4 + 4x2 + 2x1 + 1x1 texels of 3 bytes, twice
+        assert_eq!(cache.memory(), 2 * (32 + 8 + 2 + 1) * 3);
+
+        let missing = cache.load(dir.join("missing.png"), true);
+        assert!(matches!(missing, Err(TextureLoadError { path, .. }) if path.ends_with("missing.png")));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn wide_images_keep_a_row_when_downsized() {
+        let dir = std::env::temp_dir().join("rust_tracer_wide_images_keep_a_row_when_downsized");
+        std::fs::create_dir_all(&dir).unwrap();
+        RgbImage::from_pixel(64, 1, Rgb([255, 255, 255])).save(dir.join("strip.png")).unwrap();
+
+        let mut cache = TextureCache::create(16);
+        let strip = cache.texture(dir.join("strip.png"), false).unwrap();
+        let texel = strip.get_pixel(&Vec2::new(0.3, 0.5), &Vec3A::ZERO, &Footprint::default());
+        assert!((texel - Vec3A::ONE).length() < 1e-3, "{:?}", texel);
+    }
+}